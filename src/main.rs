@@ -1,33 +1,64 @@
-use amethyst::assets::{AssetStorage, Loader};
+use amethyst::assets::{AssetStorage, Format, Handle, Loader, Progress, ProgressCounter};
 use amethyst::audio::output::Output;
-use amethyst::audio::{AudioBundle, AudioSink, Source, SourceHandle, WavFormat, DjSystemDesc, Mp3Format};
+use amethyst::audio::{
+  AudioBundle, AudioEmitter, AudioListener, AudioSink, DjSystemDesc, Mp3Format, OggFormat, Source,
+  SourceHandle, WavFormat,
+};
+use amethyst::config::Config;
+use amethyst::core::ecs::storage::MaskedStorage;
 use amethyst::core::ecs::{
-  Builder, Component, DenseVecStorage, Dispatcher, DispatcherBuilder, Entities, Entity, Join, Read,
-  ReadStorage, System, SystemData, World, WorldExt, Write, WriteStorage,
+  Builder, Component, DenseVecStorage, Dispatcher, DispatcherBuilder, Entities, Entity, Join,
+  LazyUpdate, Read, ReadStorage, RunNow, Storage, System, SystemData, World, WorldExt, Write,
+  WriteStorage,
 };
-use amethyst::core::math::Vector3;
-use amethyst::core::{EventReader, Hidden, Time, Transform, TransformBundle};
+use amethyst::core::frame_limiter::{FrameLimiter, FrameRateLimitStrategy};
+use amethyst::core::math::{Point3, Vector3};
+use amethyst::core::{EventReader, Hidden, ParentHierarchy, Time, Transform, TransformBundle};
 use amethyst::derive::EventReader;
 use amethyst::input::{
-  is_close_requested, is_key_down, BindingTypes, InputBundle, InputEvent, InputHandler,
-  StringBindings, VirtualKeyCode,
+  is_close_requested, is_key_down, Axis, BindingTypes, Bindings, ControllerAxis, InputBundle,
+  InputEvent, InputHandler, StringBindings, VirtualKeyCode,
 };
+use amethyst::log::{error, info, warn};
+use amethyst::renderer::debug_drawing::DebugLines;
+use amethyst::renderer::palette::Srgba;
+use amethyst::renderer::rendy::hal::image::{Filter, SamplerInfo, WrapMode};
+use amethyst::renderer::rendy::texture::image::ImageTextureConfig;
+use amethyst::renderer::resources::Tint;
+use amethyst::renderer::transparent::Transparent;
 use amethyst::renderer::types::DefaultBackend;
 use amethyst::renderer::{
-  Camera, ImageFormat, RenderFlat2D, RenderToWindow, RenderingBundle, SpriteRender, SpriteSheet,
-  SpriteSheetFormat, Texture,
+  Camera, ImageFormat, RenderDebugLines, RenderFlat2D, RenderToWindow, RenderingBundle, Sprite,
+  SpriteRender, SpriteSheet, SpriteSheetFormat, Texture,
 };
-use amethyst::shred::ReadExpect;
+use amethyst::shred::{ReadExpect, WriteExpect};
 use amethyst::shrev::{EventChannel, ReaderId};
 use amethyst::ui::{
-  Anchor, LineMode, RenderUi, TtfFormat, UiBundle, UiCreator, UiEvent, UiFinder, UiText,
-  UiTransform,
+  Anchor, LineMode, RenderUi, Stretch, TtfFormat, UiBundle, UiCreator, UiEvent, UiFinder, UiImage,
+  UiText, UiTransform,
 };
 use amethyst::utils::application_root_dir;
-use amethyst::winit::Event;
+use amethyst::utils::fps_counter::{FpsCounter, FpsCounterBundle};
+use amethyst::window::{DisplayConfig, ScreenDimensions};
+use amethyst::winit::{Event, WindowEvent};
 use amethyst::{CoreApplication, GameData, GameDataBuilder, State, StateData, Trans};
-use rand::rngs::ThreadRng;
-use rand::{thread_rng, Rng};
+use clipboard::{ClipboardContext, ClipboardProvider};
+use image::imageops::{self, FilterType};
+use image::{Pixel, Rgba, RgbaImage};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{thread_rng, Rng, SeedableRng};
+use rusttype::{point, Font, Scale};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{create_dir_all, read_dir, OpenOptions};
+use std::io::Write as IoWrite;
+use std::ops::Deref;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use std::{iter::Cycle, vec::IntoIter};
 
 const VIRTUAL_WIDTH: f32 = 512.;
@@ -36,7 +67,12 @@ const GROUND_HEIGHT: f32 = 12.;
 const GROUND_WIDTH: f32 = 1100.;
 const BACKGROUND_SCROLL_SPEED: f32 = 30.;
 const GROUND_SCROLL_SPEED: f32 = 61.;
+/// A looping point has to be a whole number of repeats of the pattern in the texture, so the jump
+/// back is invisible, and small enough for the texture to still cover the screen right before it,
+/// see `covers_screen_while_looping`. The background repeats every 413 pixels.
 const BACKGROUND_LOOPING_POINT: f32 = 413.;
+/// The ground repeats every 8 pixels.
+const GROUND_LOOPING_POINT: f32 = 416.;
 const BACKGROUND_LOOPING_OFFSET: f32 = 290.;
 const BIRD_GRAVITY: f32 = -26.;
 const BIRD_WIDTH: f32 = 38.;
@@ -50,7 +86,120 @@ const SCORE_SOUND: &str = "audio/score.wav";
 const HURT_SOUND: &str = "audio/hurt.wav";
 const EXPLOSION_SOUND: &str = "audio/explosion.wav";
 const JUMP_SOUND: &str = "audio/jump.wav";
-const MUSIC_TRACKS: &[&str] = &["audio/marios_way.mp3"];
+const WHOOSH_SOUND: &str = "audio/whoosh.wav";
+const TICK_SOUND: &str = "audio/tick.wav";
+const COMBO_LOST_SOUND: &str = "audio/combo_lost.wav";
+const COIN_SOUND: &str = "audio/coin.wav";
+const GROUND_HIT_SOUND: &str = "audio/splat.wav";
+const PIPE_HIT_SOUND: &str = "audio/clank.wav";
+const CEILING_HIT_SOUND: &str = "audio/bonk.wav";
+const MILESTONE_SOUND: &str = "audio/chime.wav";
+const BIRD_TINTS: &[(&str, (f32, f32, f32, f32))] = &[
+  ("None", (1., 1., 1., 1.)),
+  ("Red", (1., 0.5, 0.5, 1.)),
+  ("Green", (0.5, 1., 0.5, 1.)),
+  ("Blue", (0.5, 0.7, 1., 1.)),
+  ("Gold", (1., 0.85, 0.3, 1.)),
+];
+/// Every mp3, ogg or wav file in here is part of the music cycle.
+const MUSIC_DIR: &str = "audio/music";
+const SCREENSHOT_DIR: &str = "screenshots";
+/// The share card is this many times the size of the screen.
+const SHARE_CARD_SCALE: u32 = 2;
+const SETTINGS_PATH: &str = "config/settings.ron";
+const PROFILE_COUNT: usize = 3;
+const MIN_CAMERA_ZOOM: f32 = 0.5;
+const MAX_CAMERA_ZOOM: f32 = 2.;
+const CAMERA_ZOOM_STEP: f32 = 0.1;
+const HURRY_UP_GAP_SHRINK: f32 = 0.3;
+/// Smallest gap `GameConfig::pipe_gap_min` is allowed to go down to.
+const MIN_PIPE_GAP: f32 = BIRD_HEIGHT * 2.;
+const HURRY_UP_CUE_DURATION: f32 = 2.;
+const MILESTONE_BANNER_DURATION: f32 = 1.;
+const MILESTONE_BURST_COUNT: u32 = 12;
+const MILESTONE_BURST_SPEED: f32 = 80.;
+const MILESTONE_BURST_LIFETIME: f32 = 0.6;
+const PHYSICS_STEP: f32 = 1. / 60.;
+const MAX_PHYSICS_STEPS: u32 = 5;
+/// Simulation speeds `[` and `]` step through with `GameConfig::time_scale_keys`.
+const TIME_SCALES: &[f32] = &[0.25, 0.5, 1., 2., 4.];
+/// Analog axis which flaps once it's pressed past `GameConfig::stick_flap_threshold`.
+const FLAP_AXIS: &str = "flap";
+const MENU_FONT: &str = "font/flappy.ttf";
+const UI_LAYOUT: &str = "ui/text.ron";
+const PAUSE_LAYOUT: &str = "ui/pause.ron";
+const LEADERBOARD_LAYOUT: &str = "ui/leaderboard.ron";
+const LEADERBOARD_SIZE: usize = 10;
+const PROFILER_SMOOTHING: f32 = 0.05;
+const SCREENSHOT_SEED: u64 = 2020;
+const SCREENSHOT_PIPE_X: f32 = 120.;
+const SCORE_FONT: &str = "font/font.ttf";
+const SCORE_DIGIT_WIDTH: f32 = 8.;
+const FLAP_PUFF_VELOCITIES: &[(f32, f32)] = &[(-12., -30.), (12., -30.)];
+const FLAP_PUFF_LIFETIME: f32 = 0.3;
+const DEATH_REPLAY_DURATION: f32 = 1.;
+/// Volume of the music sink at full system volume.
+const MUSIC_VOLUME: f32 = 0.125;
+/// Seconds the camera shakes after a death.
+const SHAKE_DURATION: f32 = 0.4;
+const REWIND_DURATION: f32 = 1.5;
+const TRAJECTORY_DURATION: f32 = 1.;
+/// Physics steps per dash of the predicted trajectory, every other dash is left out.
+const TRAJECTORY_DASH_STEPS: u32 = 4;
+const DEATH_REPLAY_SPEED: f32 = 0.25;
+const BOUNCE_START_POINTS: i32 = 3;
+const BOUNCE_HIT_COST: i32 = 1;
+const BOUNCE_DAMPING: f32 = 0.5;
+const BOUNCE_GRACE: f32 = 0.5;
+const JUMP_ADD_MAX: f32 = 1.5;
+const JUMP_EASE_STEPS: u32 = 3;
+const FLAP_SCALE_MIN: f32 = 0.5;
+const POWER_UP_CHANCE: f64 = 0.25;
+const POWER_UP_SIZE: f32 = 12.;
+const GRAVITY_FLIP_DURATION: f32 = 4.;
+const COIN_SIZE: f32 = 12.;
+const IMPACT_MARKER_SIZE: f32 = 8.;
+/// Seconds behind its pair a gravity well spawns, half of the shortest pause between two pairs.
+const GRAVITY_WELL_LEAD: f32 = 1.;
+/// Seconds a ring of the well takes to shrink from its edge into its center.
+const GRAVITY_WELL_PULSE: f32 = 0.8;
+const COMBO_MARGIN: f32 = 12.;
+const WIDE_PASS_MARGIN: f32 = 40.;
+const COMBO_MAX_MULTIPLIER: u32 = 3;
+const COMBO_BREAK_FLASH: f32 = 0.6;
+const COMBO_METER_WIDTH: f32 = 200.;
+const COMBO_METER_HEIGHT: f32 = 8.;
+const COMBO_METER_Y: f32 = -205.;
+const TUTORIAL_FLAPS: u32 = 3;
+const TUTORIAL_GAP: f32 = PIPE_GAP * 1.4;
+const TUTORIAL_SCROLL_FACTOR: f32 = 0.6;
+const TUTORIAL_GOAL_DURATION: f32 = 4.;
+const UI_REFERENCE_WIDTH: f32 = 1280.;
+const UI_REFERENCE_HEIGHT: f32 = 720.;
+const TITLE_LOGO_Y: f32 = 30.;
+const TITLE_BIRD_Y: f32 = -40.;
+const TITLE_BOB_SPEED: f32 = 3.;
+const TITLE_BIRD_AMPLITUDE: f32 = 8.;
+/// Sprites with a higher z are drawn in front. The ground covers the bottom ends of the pipes,
+/// the bird and its effects stay in front of everything in the world.
+const Z_BACKGROUND: f32 = 0.;
+const Z_PIPE: f32 = 1.;
+const Z_PICKUP: f32 = 1.5;
+const Z_GROUND: f32 = 2.;
+const Z_PARTICLE: f32 = 3.5;
+const Z_RHYTHM_RING: f32 = 3.8;
+const Z_BIRD: f32 = 4.;
+const Z_LOGO: f32 = 5.;
+const Z_SCORE_DIGITS: f32 = 6.;
+const Z_TUBE_MASK: f32 = 7.;
+const Z_CAMERA: f32 = 10.;
+const GAME_OVER_COUNTDOWN: f32 = 5.;
+/// Seconds a second `Escape` quits a run with `GameConfig::confirm_quit`.
+const QUIT_CONFIRM_DURATION: f32 = 2.;
+const PIPE_ENTRY_OFFSET: f32 = 60.;
+const ASSIST_STRENGTHS: &[f32] = &[0., 0.25, 0.5, 0.75, 1.];
+const ASSIST_OVERRIDE: f32 = 1.;
+const ASSIST_LOOKAHEAD: f32 = 8.;
 
 #[derive(Debug)]
 enum BackgroundType {
@@ -58,15 +207,299 @@ enum BackgroundType {
   Ground,
 }
 
+impl BackgroundType {
+  fn looping_point(&self) -> f32 {
+    match self {
+      BackgroundType::Background => BACKGROUND_LOOPING_POINT,
+      BackgroundType::Ground => GROUND_LOOPING_POINT,
+    }
+  }
+}
+
+/// Selected on the title screen and read by the play systems.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GameMode {
+  Classic,
+  /// No pipes, no score and no deaths, the bird just bumps into the ceiling and the ground.
+  Zen,
+  /// Pipes bounce the bird back and cost points, the run ends once no points are left.
+  Bounce,
+  /// A single slow pipe with hints, hitting it only restarts the current step.
+  Tutorial,
+  /// Classic turned on its side, the camera is rotated so pipes come in from the top and gravity
+  /// pulls to the right.
+  Tube,
+  /// Classic with a finish line, passing `GameConfig::sprint_pipes` pairs wins the run.
+  Sprint,
+}
+
+impl GameMode {
+  fn start_points(self) -> i32 {
+    match self {
+      GameMode::Bounce => BOUNCE_START_POINTS,
+      _ => 0,
+    }
+  }
+
+  fn label(self) -> &'static str {
+    match self {
+      GameMode::Classic => "Classic",
+      GameMode::Zen => "Zen",
+      GameMode::Bounce => "Bounce",
+      GameMode::Tutorial => "Tutorial",
+      GameMode::Tube => "Tube",
+      GameMode::Sprint => "Sprint",
+    }
+  }
+}
+
+/// Modes with a `Leaderboards` table, in the order the leaderboard tabs show them.
+const RANKED_MODES: &[GameMode] = &[GameMode::Classic, GameMode::Bounce, GameMode::Tube];
+
+impl Default for GameMode {
+  fn default() -> Self {
+    GameMode::Classic
+  }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum GameEvent {
-  Collision,
+  Collision(CollisionCause),
+  RunEnded(RunResult),
+  /// The bird flew through a pair, how close it came is measured with
+  /// `GameConfig::classify_pass`.
+  PipePassed(PassKind),
+}
+
+/// How close a pass came to the nearer pipe of the pair.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PassKind {
+  /// Closer than `GameConfig::near_miss_margin`, builds the combo.
+  Tight,
+  Normal,
+  /// Further than `GameConfig::wide_pass_margin` from both pipes.
+  Wide,
+}
+
+/// What the bird ran into, picks the sound with `GameConfig::death_sounds`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CollisionCause {
+  Ground,
+  Pipe,
+  Ceiling,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunResult {
+  score: i32,
+  duration: f32,
+  seed: u64,
+  coins: u32,
+  /// Only a `GameMode::Sprint` run which reached its target ends without a death.
+  completed: bool,
+}
+
+/// The run the game over screen shows, kept for its share card.
+#[derive(Clone, Debug)]
+struct FinishedRun {
+  result: RunResult,
+  mode: GameMode,
+  /// The run took the top of its leaderboard.
+  new_best: bool,
 }
 
 struct Score {
   text: Entity,
 }
 
+struct HurryUpCue {
+  text: Entity,
+}
+
+/// Banner of the last score milestone, see `GameConfig::milestone_interval`.
+struct Milestone {
+  text: Entity,
+  /// Highest milestone celebrated this run, a score dropping below it and climbing back doesn't
+  /// celebrate it again.
+  reached: i32,
+  /// Seconds the banner stays up.
+  remaining: f32,
+}
+
+/// Coin counter of the HUD, the count itself lives in `RunStats`.
+struct Coins {
+  text: Entity,
+}
+
+/// `PassKind::Tight` passes build a streak which multiplies the points.
+struct Combo {
+  count: u32,
+  text: Entity,
+  /// Seconds the broken combo stays on screen in red.
+  break_flash: f32,
+  /// Seconds left for the next close pass with `GameConfig::combo_timeout`.
+  remaining: f32,
+  /// Bar below the text which drains with `remaining`.
+  meter: Entity,
+}
+
+impl Combo {
+  fn multiplier(&self) -> u32 {
+    self.count.max(1).min(COMBO_MAX_MULTIPLIER)
+  }
+
+  /// Only a streak which was shown as a combo can break, it's flashed and `true` is returned.
+  fn break_streak(&mut self) -> bool {
+    let shown = self.count >= 2;
+    if shown {
+      self.break_flash = COMBO_BREAK_FLASH;
+    }
+    self.count = 0;
+    shown
+  }
+}
+
+/// Active while a grabbed power-up has gravity and flaps pointing the other way.
+struct GravityFlip {
+  remaining: f32,
+  text: Entity,
+}
+
+/// Charges from grabbed rewind power-ups, each turns one death into a rewind of
+/// `REWIND_DURATION` seconds.
+struct Rewind {
+  charges: u32,
+  text: Entity,
+}
+
+impl Rewind {
+  fn label(&self) -> String {
+    if self.charges == 0 {
+      String::new()
+    } else {
+      format!("Rewinds: {}", self.charges)
+    }
+  }
+}
+
+/// Debug builds only, toggled with `F6`: the bird flies through pipes, the ground and the ceiling
+/// and every hitbox is drawn.
+struct NoClip {
+  active: bool,
+  text: Entity,
+}
+
+/// Draws where the bird would fly over the next `TRAJECTORY_DURATION` without another flap.
+#[derive(Debug, Default)]
+struct TrajectoryPreview {
+  shown: bool,
+}
+
+/// How much the UI is scaled relative to the reference window size.
+#[derive(Debug)]
+struct UiScale {
+  factor: f32,
+}
+
+impl Default for UiScale {
+  fn default() -> Self {
+    UiScale { factor: 1. }
+  }
+}
+
+/// Fallback which mirrors the score text with sprites when the score font is missing.
+struct ScoreDigits {
+  sprite: SpriteRender,
+  shown: String,
+  digits: Vec<Entity>,
+}
+
+/// Full screen overlay which lights up on a death and fades out again.
+struct Flash {
+  overlay: Entity,
+  alpha: f32,
+}
+
+/// Camera shake of a death. The `CollisionSystem` sets its magnitude from how fast the bird
+/// crashed, it only starts once the hit ends the run.
+#[derive(Debug, Default)]
+struct Shake {
+  magnitude: f32,
+  remaining: f32,
+}
+
+/// Hides every UI widget while set, toggled with `F9` for clean screenshots.
+#[derive(Debug, Default)]
+struct HideUi {
+  hide: bool,
+}
+
+/// Root of the widgets created from `UI_LAYOUT`.
+struct UiRoot {
+  entity: Entity,
+}
+
+/// Developer diagnostics toggled with `F3`.
+struct DebugOverlay {
+  text: Entity,
+}
+
+/// Where the bird overlapped whatever ended the run, marked while the debug overlay is up and
+/// until the next run starts.
+#[derive(Debug, Default)]
+struct ImpactPoint {
+  point: Option<(f32, f32)>,
+}
+
+/// Average run times of the profiled play systems, shown on `F4`.
+struct Profiler {
+  text: Entity,
+  /// Shared with every `Profiled` system, they only measure while it's set.
+  enabled: Arc<AtomicBool>,
+  /// Microseconds per system, in the order they were added.
+  timings: Vec<(&'static str, Arc<Mutex<f32>>)>,
+}
+
+impl Profiler {
+  fn profile<S>(&mut self, name: &'static str, system: S) -> Profiled<S> {
+    let average = Arc::new(Mutex::new(0.));
+    self.timings.push((name, average.clone()));
+    Profiled {
+      system,
+      enabled: self.enabled.clone(),
+      average,
+    }
+  }
+}
+
+/// Wraps a system to time its runs for the `Profiler`, it accesses exactly what the system does.
+struct Profiled<S> {
+  system: S,
+  enabled: Arc<AtomicBool>,
+  average: Arc<Mutex<f32>>,
+}
+
+impl<'a, S: System<'a>> System<'a> for Profiled<S> {
+  type SystemData = S::SystemData;
+
+  fn run(&mut self, data: Self::SystemData) {
+    if !self.enabled.load(Ordering::Relaxed) {
+      self.system.run(data);
+      return;
+    }
+    let start = Instant::now();
+    self.system.run(data);
+    let micros = start.elapsed().as_secs_f32() * 1_000_000.;
+    if let Ok(mut average) = self.average.lock() {
+      *average += (micros - *average) * PROFILER_SMOOTHING;
+    }
+  }
+
+  fn setup(&mut self, world: &mut World) {
+    self.system.setup(world);
+  }
+}
+
 #[derive(Clone, Debug, EventReader)]
 #[reader(MyStateEventReader)]
 pub enum MyStateEvent<T = StringBindings>
@@ -84,6 +517,8 @@ where
 struct Background {
   b_type: BackgroundType,
   scroll_pos: f32,
+  /// Set once the sprite was checked against its looping point.
+  validated: bool,
 }
 
 #[derive(Debug, Default, Component)]
@@ -92,710 +527,8581 @@ struct Bird {
   dy: f32,
   score: i32,
   fly_pressed: bool,
+  /// Set while the key which started or resumed the run is still held, it doesn't count as
+  /// pressed until it's let go once.
+  held_on_entry: bool,
+  /// Separation out of a pipe the bird got bounced off, applied by the `BirdSystem`.
+  bounce: Option<Vector3<f32>>,
+  /// Seconds left in which pipes can't bounce the bird again.
+  bounce_grace: f32,
+  /// Physics steps left until an eased flap reaches its full velocity.
+  jump_ease: u32,
+  /// Seconds since the last manual flap, the flap assist holds off for `ASSIST_OVERRIDE`.
+  assist_idle: f32,
+  /// Seconds the bird has been flying, drives `GameConfig::gravity_ramp_duration`.
+  flight_time: f32,
+  /// Whether the `FLAP_AXIS` counted as held last step, see `stick_flap_down`.
+  stick_down: bool,
+}
+
+/// Rescales an analog `value` so drift within `dead_zone` reads as `0` while a full press still
+/// reads as `1`.
+fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+  let magnitude = value.abs();
+  if magnitude <= dead_zone {
+    return 0.;
+  }
+  value.signum() * ((magnitude - dead_zone) / (1. - dead_zone)).min(1.)
+}
+
+/// Whether the `FLAP_AXIS` counts as held at `value`. Once it's past the `threshold` it's held
+/// until it falls below half of it, so jitter right at the threshold can't flap twice.
+fn stick_flap_down(value: f32, was_down: bool, dead_zone: f32, threshold: f32) -> bool {
+  let value = apply_dead_zone(value, dead_zone);
+  if was_down {
+    value >= threshold / 2.
+  } else {
+    value >= threshold
+  }
+}
+
+impl Bird {
+  /// Feeds whether the primary action is `down` this step to the edge detection and returns if
+  /// that's a flap. A key held since `held_on_entry` doesn't count until it's let go.
+  fn flap_triggered(&mut self, down: bool, trigger: FlapTrigger) -> bool {
+    if !down {
+      self.held_on_entry = false;
+    }
+    let pressed = down && !self.held_on_entry;
+    let flap = match trigger {
+      FlapTrigger::Press => pressed && !self.fly_pressed,
+      FlapTrigger::Release => !pressed && self.fly_pressed,
+    };
+    self.fly_pressed = pressed;
+    flap
+  }
+
+  /// The key is still held from another state, neither it nor letting it go is a flap.
+  fn hold_until_released(&mut self) {
+    self.held_on_entry = true;
+    self.fly_pressed = false;
+  }
 }
 
 #[derive(Debug, Default, Component)]
 #[storage(DenseVecStorage)]
 struct Pipe {
   is_scored: bool,
+  /// Set once the bird has entered the gap and the whoosh was played.
+  whooshed: bool,
+}
+
+/// Zone which pulls the bird towards its center while it's closer than `radius`, drawn as a
+/// ring shrinking into it.
+#[derive(Debug, Default, Component)]
+#[storage(DenseVecStorage)]
+struct GravityWell {
+  radius: f32,
+  strength: f32,
+  /// Progress of the ring from the edge to the center between `0` and `1`.
+  phase: f32,
+}
+
+/// Collectible floating in a gap, what grabbing it does depends on its kind.
+#[derive(Debug, Default, Component)]
+#[storage(DenseVecStorage)]
+struct PowerUp {
+  kind: PowerUpKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PowerUpKind {
+  /// Flips gravity for `GRAVITY_FLIP_DURATION`.
+  GravityFlip,
+  /// Adds a `Rewind` charge.
+  Rewind,
+}
+
+impl Default for PowerUpKind {
+  fn default() -> Self {
+    PowerUpKind::GravityFlip
+  }
+}
+
+/// Collectible floating in a gap which only counts towards `RunStats::coins`.
+#[derive(Debug, Default, Component)]
+#[storage(DenseVecStorage)]
+struct Coin;
+
+/// Slides a freshly spawned pipe towards `target_y` once it scrolls into view, removed when done.
+#[derive(Debug, Default, Component)]
+#[storage(DenseVecStorage)]
+struct PipeEntry {
+  target_y: f32,
+  /// Where the pipe starts relative to `target_y`, away from the gap.
+  offset: f32,
+  elapsed: f32,
+}
+
+/// A short lived sprite which drifts with a constant velocity and fades out over its lifetime.
+#[derive(Debug, Component)]
+#[storage(DenseVecStorage)]
+struct Particle {
+  dx: f32,
+  dy: f32,
+  age: f32,
+  lifetime: f32,
+}
+
+/// Copy of the `source` text drawn slightly offset behind it with `GameConfig::text_shadow`.
+#[derive(Debug, Component)]
+#[storage(DenseVecStorage)]
+struct TextShadow {
+  source: Entity,
+}
+
+/// Layout a UI text was authored with at `UI_REFERENCE_WIDTH` x `UI_REFERENCE_HEIGHT`.
+#[derive(Debug, Component)]
+#[storage(DenseVecStorage)]
+struct UiBaseLayout {
+  font_size: f32,
+  x: f32,
+  y: f32,
+  width: f32,
+  height: f32,
+}
+
+/// Title screen entities which float up and down around `base_y`.
+#[derive(Debug, Component)]
+#[storage(DenseVecStorage)]
+struct TitleBob {
+  base_y: f32,
+  amplitude: f32,
+  phase: f32,
+}
+
+struct ParticleSprite {
+  sprite: SpriteRender,
+}
+
+/// Practice aid which expands a ring around the bird on every beat.
+struct RhythmGuide {
+  sprite: SpriteRender,
+  ring: Option<Entity>,
+  /// Progress through the current beat from `0` to `1`.
+  phase: f32,
 }
 
 struct Sounds {
   score_sfx: SourceHandle,
   hurt_sfx: SourceHandle,
   explosion_sfx: SourceHandle,
-  jump_sfx: SourceHandle,
+  /// One of them is picked at random for every flap.
+  jump_sfx: Vec<SourceHandle>,
+  whoosh_sfx: SourceHandle,
+  tick_sfx: SourceHandle,
+  combo_lost_sfx: SourceHandle,
+  coin_sfx: SourceHandle,
+  ground_hit_sfx: SourceHandle,
+  pipe_hit_sfx: SourceHandle,
+  ceiling_hit_sfx: SourceHandle,
+  milestone_sfx: SourceHandle,
 }
 
+/// The DJ picks its tracks with `next_track`, which stays silent without any to play.
 struct Music {
   pub music: Cycle<IntoIter<SourceHandle>>,
+  /// Asset paths of the tracks in the order they're cycled.
+  pub tracks: Vec<String>,
+  /// Tracks which failed to load are skipped by the DJ, once all of them did it gives up.
+  pub loading: ProgressCounter,
 }
 
-struct BackgroundSystem;
-
-impl<'a> System<'a> for BackgroundSystem {
-  type SystemData = (
-    WriteStorage<'a, Background>,
-    WriteStorage<'a, Transform>,
-    Read<'a, Time>,
-  );
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum FrameCap {
+  Fps30,
+  Fps60,
+  Fps144,
+  Uncapped,
+}
 
-  fn run(&mut self, (mut backgrounds, mut transforms, time): Self::SystemData) {
-    for (background, transform) in (&mut backgrounds, &mut transforms).join() {
-      match background.b_type {
-        BackgroundType::Background => {
-          background.scroll_pos = (background.scroll_pos
-            + BACKGROUND_SCROLL_SPEED * time.delta_seconds())
-            % BACKGROUND_LOOPING_POINT;
-          transform.set_translation_x(BACKGROUND_LOOPING_OFFSET - background.scroll_pos);
-        }
-        BackgroundType::Ground => {
-          background.scroll_pos = (background.scroll_pos
-            + GROUND_SCROLL_SPEED * time.delta_seconds())
-            % BACKGROUND_LOOPING_POINT;
-          transform.set_translation_x(BACKGROUND_LOOPING_OFFSET - background.scroll_pos);
-        }
-      }
+impl FrameCap {
+  fn next(self) -> Self {
+    match self {
+      FrameCap::Fps30 => FrameCap::Fps60,
+      FrameCap::Fps60 => FrameCap::Fps144,
+      FrameCap::Fps144 => FrameCap::Uncapped,
+      FrameCap::Uncapped => FrameCap::Fps30,
     }
   }
-}
 
-struct BirdSystem;
+  fn previous(self) -> Self {
+    match self {
+      FrameCap::Fps30 => FrameCap::Uncapped,
+      FrameCap::Fps60 => FrameCap::Fps30,
+      FrameCap::Fps144 => FrameCap::Fps60,
+      FrameCap::Uncapped => FrameCap::Fps144,
+    }
+  }
 
-impl<'a> System<'a> for BirdSystem {
-  type SystemData = (
-    WriteStorage<'a, Bird>,
-    WriteStorage<'a, Transform>,
-    Read<'a, Time>,
-    Read<'a, InputHandler<StringBindings>>,
-    Read<'a, AssetStorage<Source>>,
-    ReadExpect<'a, Sounds>,
-    Option<Read<'a, Output>>,
-  );
+  fn limit(self) -> (FrameRateLimitStrategy, u32) {
+    match self {
+      FrameCap::Fps30 => (FrameRateLimitStrategy::Yield, 30),
+      FrameCap::Fps60 => (FrameRateLimitStrategy::Yield, 60),
+      FrameCap::Fps144 => (FrameRateLimitStrategy::Yield, 144),
+      FrameCap::Uncapped => (FrameRateLimitStrategy::Unlimited, 0),
+    }
+  }
 
-  fn run(
-    &mut self,
-    (mut birds, mut transforms, time, input, storage, sounds, output): Self::SystemData,
-  ) {
-    for (bird, transform) in (&mut birds, &mut transforms).join() {
-      bird.dy += BIRD_GRAVITY * time.delta_seconds();
-      let space_pressed = input.key_is_down(VirtualKeyCode::Space);
-      if space_pressed && space_pressed != bird.fly_pressed {
-        bird.dy = BIRD_JUMP;
-        play_jump_sound(&*sounds, &storage, output.as_deref());
-      }
-      bird.fly_pressed = space_pressed;
-      transform.prepend_translation_y(bird.dy);
+  fn label(self) -> &'static str {
+    match self {
+      FrameCap::Fps30 => "30",
+      FrameCap::Fps60 => "60",
+      FrameCap::Fps144 => "144",
+      FrameCap::Uncapped => "Uncapped",
     }
   }
 }
 
-struct PipeSystem;
+/// Player choices which are persisted to `SETTINGS_PATH` whenever they change.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct Settings {
+  camera_zoom: f32,
+  frame_cap: FrameCap,
+  one_button_mode: bool,
+  /// Pauses a run as soon as the window loses focus.
+  auto_pause: bool,
+  /// Pulses a ring around the bird at the flap cadence of `GameConfig::rhythm_bpm`.
+  rhythm_guide: bool,
+  /// RGBA multiplied with the bird sprite, the menu cycles through `BIRD_TINTS`.
+  bird_tint: (f32, f32, f32, f32),
+  /// Cleared from the settings menu to play the tutorial again on the next run.
+  tutorial_completed: bool,
+  /// How close to the next gap center the bird is flapped automatically, 0 turns it off.
+  assist_strength: f32,
+  /// Index of the profile whose `Records` are loaded, below `PROFILE_COUNT`.
+  profile: usize,
+  /// Hides every text of `GameConfig::hud` during a run, toggled with `H`.
+  hide_hud: bool,
+}
 
-impl<'a> System<'a> for PipeSystem {
-  type SystemData = (
-    Entities<'a>,
-    ReadStorage<'a, Pipe>,
-    WriteStorage<'a, Transform>,
-    Read<'a, Time>,
-  );
+impl Settings {
+  fn bird_tint(&self) -> Tint {
+    let (r, g, b, a) = self.bird_tint;
+    Tint(Srgba::new(r, g, b, a))
+  }
 
-  fn run(&mut self, (entities, pipes, mut transforms, time): Self::SystemData) {
-    for (e, _, transform) in (&entities, &pipes, &mut transforms).join() {
-      transform.prepend_translation_x(PIPE_SCROLL * time.delta_seconds());
-      if transform.translation().x < VIRTUAL_WIDTH / -2. - PIPE_WIDTH {
-        entities
-          .delete(e)
-          .expect("Error while removing non existing entity! This should never happened!");
-      }
+  fn bird_tint_label(&self) -> &'static str {
+    BIRD_TINTS
+      .iter()
+      .find(|(_, tint)| *tint == self.bird_tint)
+      .map_or("Custom", |(name, _)| name)
+  }
+
+  /// Moves through `BIRD_TINTS`, a custom tint continues with the first preset.
+  fn cycle_bird_tint(&mut self, forward: bool) {
+    let count = BIRD_TINTS.len();
+    let next = match BIRD_TINTS
+      .iter()
+      .position(|(_, tint)| *tint == self.bird_tint)
+    {
+      Some(i) if forward => (i + 1) % count,
+      Some(i) => (i + count - 1) % count,
+      None => 0,
+    };
+    self.bird_tint = BIRD_TINTS[next].1;
+  }
+
+  fn assist_label(&self) -> String {
+    if self.assist_strength > 0. {
+      format!("{}%", (self.assist_strength * 100.).round())
+    } else {
+      "Off".to_string()
     }
   }
+
+  /// Moves through `ASSIST_STRENGTHS`, a strength set by hand continues with the closest smaller one.
+  fn cycle_assist(&mut self, forward: bool) {
+    let count = ASSIST_STRENGTHS.len();
+    let current = ASSIST_STRENGTHS
+      .iter()
+      .rposition(|strength| *strength <= self.assist_strength)
+      .unwrap_or(0);
+    let next = if forward {
+      (current + 1) % count
+    } else {
+      (current + count - 1) % count
+    };
+    self.assist_strength = ASSIST_STRENGTHS[next];
+  }
 }
 
-struct CollisionSystem;
+/// Bests of a profile which are persisted to its `records_path` whenever one is beaten.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Records {
+  best_coins: u32,
+  leaderboards: Leaderboards,
+}
 
-impl<'a> System<'a> for CollisionSystem {
-  type SystemData = (
-    ReadStorage<'a, Bird>,
-    ReadStorage<'a, Background>,
-    ReadStorage<'a, Pipe>,
-    ReadStorage<'a, Transform>,
-    Write<'a, EventChannel<GameEvent>>,
-    Read<'a, AssetStorage<Source>>,
-    ReadExpect<'a, Sounds>,
-    Option<Read<'a, Output>>,
-  );
+/// The best runs of every mode in `RANKED_MODES`, each table sorted best first and at most
+/// `LEADERBOARD_SIZE` long.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct Leaderboards {
+  classic: Vec<ScoreEntry>,
+  bounce: Vec<ScoreEntry>,
+  tube: Vec<ScoreEntry>,
+}
 
-  fn run(
-    &mut self,
-    (birds, backgrounds, pipes, transforms, mut event_ch, storage, sounds, output): Self::SystemData,
-  ) {
-    for (_, transform) in (&birds, &transforms).join() {
-      let bird_x = transform.translation().x;
-      let bird_y = transform.translation().y;
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct ScoreEntry {
+  score: i32,
+  /// Seconds since the unix epoch.
+  date: u64,
+}
 
-      if bird_y - BIRD_WIDTH / 2. > VIRTUAL_HEIGHT / 2. {
-        event_ch.single_write(GameEvent::Collision);
-        play_hurt_sound(&*sounds, &storage, output.as_deref());
-      }
+impl Leaderboards {
+  fn table(&self, mode: GameMode) -> &[ScoreEntry] {
+    match mode {
+      GameMode::Classic => &self.classic,
+      GameMode::Bounce => &self.bounce,
+      GameMode::Tube => &self.tube,
+      GameMode::Zen | GameMode::Tutorial | GameMode::Sprint => &[],
+    }
+  }
 
-      for (_, transform) in (&pipes, &transforms).join() {
-        let pipe_x = transform.translation().x - (PIPE_WIDTH / 2.);
-        let pipe_y = transform.translation().y - (PIPE_HEIGHT / 2.);
+  /// Returns the rank the entry got, `None` if the mode isn't ranked or the table is full of
+  /// better runs. Ties go below the runs which got there first.
+  fn insert(&mut self, mode: GameMode, entry: ScoreEntry) -> Option<usize> {
+    let table = match mode {
+      GameMode::Classic => &mut self.classic,
+      GameMode::Bounce => &mut self.bounce,
+      GameMode::Tube => &mut self.tube,
+      GameMode::Zen | GameMode::Tutorial | GameMode::Sprint => return None,
+    };
+    let rank = table
+      .iter()
+      .position(|other| entry.score > other.score)
+      .unwrap_or_else(|| table.len());
+    if rank >= LEADERBOARD_SIZE {
+      return None;
+    }
+    table.insert(rank, entry);
+    table.truncate(LEADERBOARD_SIZE);
+    Some(rank)
+  }
+}
 
-        if point_in_rect(
-          bird_x,
-          bird_y,
-          pipe_x - BIRD_WIDTH / 2.,
-          pipe_y - BIRD_HEIGHT / 2.,
-          pipe_x + PIPE_WIDTH + BIRD_WIDTH / 2.,
-          pipe_y + PIPE_HEIGHT + BIRD_HEIGHT / 2.,
-        ) {
-          event_ch.single_write(GameEvent::Collision);
-          play_hurt_sound(&*sounds, &storage, output.as_deref());
+impl Default for Settings {
+  fn default() -> Self {
+    Settings {
+      camera_zoom: 1.,
+      frame_cap: FrameCap::Fps144,
+      one_button_mode: false,
+      auto_pause: true,
+      rhythm_guide: false,
+      bird_tint: (1., 1., 1., 1.),
+      tutorial_completed: false,
+      assist_strength: 0.,
+      profile: 0,
+      hide_hud: false,
+    }
+  }
+}
+
+/// Gameplay systems always advance by `PHYSICS_STEP`, no matter how long a frame took, so the
+/// frame cap has no influence on how the bird flies.
+#[derive(Debug, Default)]
+struct PhysicsClock {
+  accumulator: f32,
+  steps: u32,
+  lagging: bool,
+  /// A fast bird's step is split into `GameConfig::physics_substeps`, `substep` counts from `0`.
+  substeps: u32,
+  substep: u32,
+}
+
+impl PhysicsClock {
+  /// Returns how many physics steps have to run for a frame which took `delta` seconds. If the
+  /// simulation can't keep up the remaining time is dropped instead of piling up.
+  fn advance(&mut self, delta: f32) -> u32 {
+    self.accumulator += delta;
+    self.steps = 0;
+    while self.accumulator >= PHYSICS_STEP && self.steps < MAX_PHYSICS_STEPS {
+      self.accumulator -= PHYSICS_STEP;
+      self.steps += 1;
+    }
+    self.lagging = self.accumulator >= PHYSICS_STEP;
+    if self.lagging {
+      self.accumulator = 0.;
+    }
+    self.steps
+  }
+}
+
+/// Placement of the texts shown during a run, e.g. to make room for a stream overlay.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct HudLayout {
+  score: HudElement,
+  /// The combo text and its meter.
+  combo: HudElement,
+  /// The coin counter and the rewind charges below it.
+  coins: HudElement,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct HudElement {
+  visible: bool,
+  /// Moves the element away from its usual spot, the sprite digits of the score stay put.
+  x: f32,
+  y: f32,
+}
+
+impl Default for HudElement {
+  fn default() -> Self {
+    HudElement {
+      visible: true,
+      x: 0.,
+      y: 0.,
+    }
+  }
+}
+
+impl HudElement {
+  fn place(self, mut transform: UiTransform) -> UiTransform {
+    transform.local_x += self.x;
+    transform.local_y += self.y;
+    transform
+  }
+}
+
+/// Tuning values loaded from `config/game.ron`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct GameConfig {
+  /// Seconds a run has to last before things start to speed up.
+  hurry_up_threshold: f32,
+  /// Intensity gained per second once the threshold is passed.
+  hurry_up_rate: f32,
+  /// Upper bound of the intensity so runs stay humanly playable.
+  hurry_up_max: f32,
+  /// How many pixels the bird may dip into the ground before it counts as a collision.
+  ground_collision_tolerance: f32,
+  /// Appends every finished run as a JSON line to `run_log_path`.
+  run_log_enabled: bool,
+  /// Relative to the application root.
+  run_log_path: String,
+  /// Width of the pipe hitbox, the sprite is always drawn `PIPE_WIDTH` wide.
+  pipe_collision_width: f32,
+  /// Replays the last second before a death in slow motion before the score is shown.
+  death_replay_enabled: bool,
+  /// Sampling used for every sprite texture, `Nearest` keeps the pixel art crisp.
+  texture_filter: TextureFilter,
+  /// Vertical velocity the bird starts every run with, positive values make it rise first.
+  start_dy: f32,
+  /// Plays a whoosh when the bird enters a gap, the score chime still follows in the middle.
+  pipe_whoosh_enabled: bool,
+  /// Plays a sound when a combo streak breaks, the red flash of the combo text is always shown.
+  combo_break_sound: bool,
+  /// Seconds a combo waits for the next close pass before it breaks, `None` never breaks it
+  /// by time.
+  combo_timeout: Option<f32>,
+  /// `Right` mirrors the world, pipes come in from the left and the bird faces left.
+  scroll_direction: ScrollDirection,
+  /// Which frame of the pipe sprite sheet a pipe pair is drawn with.
+  pipe_frames: PipeFrames,
+  /// Seconds at the start of a run in which pipes are ignored, `0` turns it off.
+  start_grace_period: f32,
+  /// Spawns the gravity flipping power-up in some gaps.
+  power_ups_enabled: bool,
+  /// Pipe pairs spawned at once, each new wave starts after the last one.
+  pipe_wave_count: u32,
+  /// Horizontal distance between the pairs of a wave.
+  pipe_wave_spacing: f32,
+  /// Flaps per minute the rhythm guide pulses with before the hurry up speeds it up.
+  rhythm_bpm: f32,
+  /// Ticks on every beat while the rhythm guide is shown.
+  rhythm_tick_sound: bool,
+  /// RGBA the window is cleared with behind the background.
+  clear_color: (f32, f32, f32, f32),
+  /// Flashes the screen when the bird dies.
+  flash_enabled: bool,
+  flash_color: (f32, f32, f32),
+  flash_peak_alpha: f32,
+  /// Seconds the flash takes to fade out from its peak.
+  flash_decay: f32,
+  /// Pixels the camera shakes on a death per pixel per step the bird fell at, up to
+  /// `impact_shake_max` pixels.
+  impact_shake_scale: f32,
+  impact_shake_max: f32,
+  /// How a flap changes the vertical velocity of the bird.
+  jump_mode: JumpMode,
+  /// Distance a pipe has to be past the visible edge before it's despawned.
+  despawn_margin: f32,
+  /// Chance of a coin in a gap which didn't get a power-up.
+  coin_chance: f64,
+  /// Flying off the top brings the bird back in above the ground instead of killing it.
+  ceiling_wrap: bool,
+  /// Pans the jump, score and hurt sounds with the position of the bird.
+  spatial_audio: bool,
+  /// Seconds the logo is shown at launch before the title, `0` goes straight to the title.
+  splash_duration: f32,
+  /// Moves a new gap closer to the last one if the bird couldn't climb or drop to it in time, a
+  /// scripted level is left as written.
+  reachability_check: bool,
+  /// The bird tumbles to the ground after a collision before the run ends, instead of the game
+  /// over showing right away.
+  death_fall: bool,
+  /// How the score is written, the sprite digits fallback only shows its digits.
+  score_format: ScoreFormat,
+  /// Wav files in `assets` a flap picks its sound from at random.
+  jump_sounds: Vec<String>,
+  /// Passing pipes stops counting at this score, `None` counts until `i32::MAX`.
+  max_score: Option<i32>,
+  /// Plays the tracks in `assets/audio/music` in a new random order each launch.
+  shuffle_music: bool,
+  /// Scales the music with the master volume of the system, where it can be read.
+  adaptive_volume: bool,
+  /// Seconds until the first pipe of a run spawns, later ones follow every 2 to 4 seconds.
+  first_pipe_delay: f32,
+  /// Seconds the game over screen waits for a retry before it goes back to the title, `None`
+  /// waits forever.
+  game_over_timeout: Option<f32>,
+  /// Saves a share card of every run which is a new best, once its banner is up.
+  auto_share_card: bool,
+  /// Seconds a new pipe takes to slide in from above or below, 0 turns it off.
+  pipe_entry_duration: f32,
+  /// Pipes can be hit while they're still sliding in.
+  pipe_entry_collision: bool,
+  /// Share of the `FLAP_AXIS` travel ignored as drift, and how far past it the axis has to be
+  /// pressed to flap.
+  stick_dead_zone: f32,
+  stick_flap_threshold: f32,
+  /// RON file in `assets` with a `Level` of gap centers, `None` places them at random.
+  level: Option<String>,
+  /// A hit on the ground, a pipe or the ceiling each plays its own sound instead of the
+  /// explosion.
+  death_sounds: bool,
+  /// Spawns the rewind power-up in some gaps, a death with a charge left rewinds the run
+  /// instead of ending it.
+  rewind_enabled: bool,
+  hud: HudLayout,
+  /// Seconds over which gravity grows to its full strength at the start of a run, `0` turns
+  /// the ramp off.
+  gravity_ramp_duration: f32,
+  /// Fraction of the full gravity the ramp starts with.
+  gravity_ramp_start: f32,
+  /// Practice aids for new players, e.g. the trajectory preview on `L`.
+  beginner_hints: bool,
+  /// Range every pair picks its gap size from before the hurry up shrinks it, both at
+  /// `PIPE_GAP` keep every gap the same.
+  pipe_gap_min: f32,
+  pipe_gap_max: f32,
+  /// Whether the primary action flaps when it's pressed or when it's released.
+  flap_trigger: FlapTrigger,
+  /// Collides pipes and the ground with a circle of this radius around the bird instead of its
+  /// `BIRD_WIDTH` by `BIRD_HEIGHT` rectangle.
+  bird_collision_radius: Option<f32>,
+  /// Pauses a run when a gamepad disconnects, it resumes once one is connected again.
+  pause_on_disconnect: bool,
+  /// Splits a physics step into this many, so a bird faster than `substep_speed` pixels per step
+  /// can't pass through the ground in between two collision checks.
+  physics_substeps: u32,
+  substep_speed: f32,
+  /// Celebrates every time the score reaches another multiple of it, `None` never does.
+  milestone_interval: Option<u32>,
+  milestone_effects: MilestoneEffects,
+  /// Lets `[` and `]` slow down and speed up the game for testing, the audio keeps its speed.
+  time_scale_keys: bool,
+  /// Passes closer than this to a pipe are near misses which build the combo.
+  near_miss_margin: f32,
+  /// Passes further than this from both pipes are wide ones.
+  wide_pass_margin: f32,
+  /// How much harder a flap pushes while the bird falls at `BIRD_JUMP` and how much softer
+  /// while it rises as fast, `0` always flaps with `BIRD_JUMP`. Only the `Set` and `Add` jump
+  /// modes scale.
+  flap_fall_scaling: f32,
+  /// Holds the music while the game is paused instead of playing on under the pause menu.
+  pause_music: bool,
+  /// Pipe pairs a `GameMode::Sprint` run has to pass to be won.
+  sprint_pipes: u32,
+  /// Drop shadow drawn behind the HUD texts so they stay readable on bright backgrounds, `None`
+  /// draws none.
+  text_shadow: Option<TextShadowStyle>,
+  /// Draws the score with the TTF font or with the chunky sprite digits.
+  score_style: ScoreStyle,
+  /// Tints both pipes of a pair once the bird flew through it, `None` leaves them as they are.
+  scored_pipe_tint: Option<(f32, f32, f32, f32)>,
+  /// `Escape` during a run asks to be pressed again before it quits. Either way the run is
+  /// recorded first.
+  confirm_quit: bool,
+  /// Places gravity wells between some of the pairs, `None` never does.
+  gravity_wells: Option<GravityWells>,
+  /// Pixels the bird on the title screen floats up and down.
+  title_bob_amplitude: f32,
+  /// Radians per second the title screen bird and logo float with.
+  title_bob_speed: f32,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct GravityWells {
+  /// Chance of a well behind a pair.
+  chance: f64,
+  radius: f32,
+  /// Pull at the center, fading out towards the edge. `BIRD_GRAVITY` is 26.
+  strength: f32,
+}
+
+impl Default for GravityWells {
+  fn default() -> Self {
+    GravityWells {
+      chance: 0.3,
+      radius: 60.,
+      strength: 20.,
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum ScoreStyle {
+  /// `SCORE_FONT`, the digits are still used if it's missing.
+  Font,
+  /// The sprites of `texture/digits.png`, centered at the top of the screen.
+  Digits,
+}
+
+/// Offset is in UI pixels at `UI_REFERENCE_WIDTH` x `UI_REFERENCE_HEIGHT`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+struct TextShadowStyle {
+  offset: (f32, f32),
+  color: (f32, f32, f32, f32),
+}
+
+/// What happens when the score reaches a `GameConfig::milestone_interval`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+struct MilestoneEffects {
+  sound: bool,
+  /// A burst of particles around the bird.
+  particles: bool,
+  /// The milestone written across the middle of the screen.
+  banner: bool,
+}
+
+impl Default for MilestoneEffects {
+  fn default() -> Self {
+    MilestoneEffects {
+      sound: true,
+      particles: true,
+      banner: true,
+    }
+  }
+}
+
+/// A fixed sequence of gap centers loaded from `GameConfig::level`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct Level {
+  /// Relative to the center of the screen, like the random ones which lie within -40 to 40.
+  gaps: Vec<f32>,
+  when_done: LevelEnd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum LevelEnd {
+  /// Starts over with the first gap.
+  Loop,
+  /// No more pipes spawn, the run ends once the last one has scrolled out.
+  EndRun,
+}
+
+#[derive(Clone, Debug)]
+enum GapSource {
+  Random,
+  Scripted(Level),
+}
+
+impl Default for GapSource {
+  fn default() -> Self {
+    GapSource::Random
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum ScoreFormat {
+  /// `1234567`
+  Plain,
+  /// `1,234,567`
+  Separated,
+  /// `1.2M`
+  Suffix,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum JumpMode {
+  /// The velocity becomes `BIRD_JUMP` instantly.
+  Set,
+  /// `BIRD_JUMP` is added to the current velocity, capped at `JUMP_ADD_MAX` times of it.
+  Add,
+  /// The velocity blends towards `BIRD_JUMP` over `JUMP_EASE_STEPS` physics steps.
+  Eased,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum FlapTrigger {
+  /// Flaps as soon as the key goes down.
+  Press,
+  /// Flaps once the key is let go again, holding it does nothing.
+  Release,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum PipeFrames {
+  /// Always the first frame.
+  First,
+  /// A random frame per pair.
+  Random,
+  /// Cycles through the frames pair by pair.
+  Alternate,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum ScrollDirection {
+  Left,
+  Right,
+}
+
+impl ScrollDirection {
+  /// Multiplied with x coordinates and velocities which assume the world scrolls to the left.
+  fn sign(self) -> f32 {
+    match self {
+      ScrollDirection::Left => 1.,
+      ScrollDirection::Right => -1.,
+    }
+  }
+}
+
+impl GameConfig {
+  /// Clamps values which would make a run unplayable and warns about them.
+  fn validated(mut self) -> Self {
+    let max_start_dy =
+      (2. * -BIRD_GRAVITY * PHYSICS_STEP * (VIRTUAL_HEIGHT / 2. - BIRD_HEIGHT / 2.)).sqrt();
+    if self.start_dy > max_start_dy {
+      warn!(
+        "start_dy of {} would carry the bird off the screen, using {} instead.",
+        self.start_dy, max_start_dy
+      );
+      self.start_dy = max_start_dy;
+    }
+    // The bird needs room to get from one gap to the next between two pairs of a wave.
+    let min_wave_spacing = PIPE_WIDTH + 2. * BIRD_WIDTH;
+    if self.pipe_wave_count > 1 && self.pipe_wave_spacing < min_wave_spacing {
+      warn!(
+        "pipe_wave_spacing of {} leaves no room between the pipes, using {} instead.",
+        self.pipe_wave_spacing, min_wave_spacing
+      );
+      self.pipe_wave_spacing = min_wave_spacing;
+    }
+    let (r, g, b, a) = self.clear_color;
+    if [r, g, b, a].iter().any(|c| !(0. ..=1.).contains(c)) {
+      warn!(
+        "clear_color {:?} has components outside of 0 to 1, they are clamped.",
+        self.clear_color
+      );
+      let clamp = |c: f32| c.max(0.).min(1.);
+      self.clear_color = (clamp(r), clamp(g), clamp(b), clamp(a));
+    }
+    if self.impact_shake_scale < 0. {
+      warn!(
+        "impact_shake_scale of {} is negative, using 0 instead.",
+        self.impact_shake_scale
+      );
+      self.impact_shake_scale = 0.;
+    }
+    if self.despawn_margin < 0. {
+      warn!(
+        "despawn_margin of {} would remove pipes while they're visible, using 0 instead.",
+        self.despawn_margin
+      );
+      self.despawn_margin = 0.;
+    }
+    if self.first_pipe_delay < 0. {
+      warn!(
+        "first_pipe_delay of {} is negative, using 0 instead.",
+        self.first_pipe_delay
+      );
+      self.first_pipe_delay = 0.;
+    }
+    if self.pipe_entry_duration < 0. {
+      warn!(
+        "pipe_entry_duration of {} is negative, using 0 instead.",
+        self.pipe_entry_duration
+      );
+      self.pipe_entry_duration = 0.;
+    }
+    if let Some(timeout) = self.combo_timeout.filter(|timeout| *timeout <= 0.) {
+      warn!(
+        "combo_timeout of {} would break every combo right away, using 1 instead.",
+        timeout
+      );
+      self.combo_timeout = Some(1.);
+    }
+    if let Some(wells) = self.gravity_wells.as_mut() {
+      if wells.radius <= 0. {
+        warn!(
+          "gravity_wells radius of {} can't reach the bird, using {} instead.",
+          wells.radius,
+          GravityWells::default().radius
+        );
+        wells.radius = GravityWells::default().radius;
+      }
+      wells.chance = wells.chance.max(0.).min(1.);
+    }
+    if self.flap_fall_scaling < 0. {
+      warn!(
+        "flap_fall_scaling of {} is negative, using 0 instead.",
+        self.flap_fall_scaling
+      );
+      self.flap_fall_scaling = 0.;
+    }
+    if self.near_miss_margin < 0. {
+      warn!(
+        "near_miss_margin of {} is negative, using 0 instead.",
+        self.near_miss_margin
+      );
+      self.near_miss_margin = 0.;
+    }
+    if self.wide_pass_margin < self.near_miss_margin {
+      warn!(
+        "wide_pass_margin of {} is below the near_miss_margin, using {} instead.",
+        self.wide_pass_margin, self.near_miss_margin
+      );
+      self.wide_pass_margin = self.near_miss_margin;
+    }
+    if let Some(timeout) = self.game_over_timeout.filter(|timeout| *timeout < 1.) {
+      warn!(
+        "game_over_timeout of {} leaves no time to retry, using 1 instead.",
+        timeout
+      );
+      self.game_over_timeout = Some(1.);
+    }
+    if let Some(max_score) = self.max_score.filter(|max| *max < 1) {
+      warn!(
+        "max_score of {} leaves nothing to score, using 1 instead.",
+        max_score
+      );
+      self.max_score = Some(1);
+    }
+    if !(0. ..=1.).contains(&self.coin_chance) {
+      warn!(
+        "coin_chance of {} isn't a probability, it's clamped to 0 to 1.",
+        self.coin_chance
+      );
+      self.coin_chance = self.coin_chance.max(0.).min(1.);
+    }
+    if !(0. ..1.).contains(&self.stick_dead_zone) {
+      warn!(
+        "stick_dead_zone of {} leaves no travel to flap with, using 0.15 instead.",
+        self.stick_dead_zone
+      );
+      self.stick_dead_zone = 0.15;
+    }
+    if self.stick_flap_threshold <= 0. || self.stick_flap_threshold > 1. {
+      warn!(
+        "stick_flap_threshold of {} isn't within the axis travel, using 0.5 instead.",
+        self.stick_flap_threshold
+      );
+      self.stick_flap_threshold = 0.5;
+    }
+    if self.pipe_gap_min < MIN_PIPE_GAP {
+      warn!(
+        "pipe_gap_min of {} is too tight to fly through, using {} instead.",
+        self.pipe_gap_min, MIN_PIPE_GAP
+      );
+      self.pipe_gap_min = MIN_PIPE_GAP;
+    }
+    if self.pipe_gap_max < self.pipe_gap_min {
+      warn!(
+        "pipe_gap_max of {} is below pipe_gap_min, using {} instead.",
+        self.pipe_gap_max, self.pipe_gap_min
+      );
+      self.pipe_gap_max = self.pipe_gap_min;
+    }
+    if self.milestone_interval == Some(0) {
+      warn!("milestone_interval of 0 would celebrate every point, turning milestones off.");
+      self.milestone_interval = None;
+    }
+    if self.sprint_pipes == 0 {
+      warn!("sprint_pipes of 0 would win a sprint before it started, using 1 instead.");
+      self.sprint_pipes = 1;
+    }
+    if let Some(radius) = self.bird_collision_radius.filter(|radius| *radius <= 0.) {
+      warn!(
+        "bird_collision_radius of {} can't hit anything, using the rectangle instead.",
+        radius
+      );
+      self.bird_collision_radius = None;
+    }
+    if self.physics_substeps == 0 {
+      warn!("physics_substeps of 0 would stop the bird, using 1 instead.");
+      self.physics_substeps = 1;
+    }
+    if self.gravity_ramp_duration < 0. {
+      warn!(
+        "gravity_ramp_duration of {} is negative, using 0 instead.",
+        self.gravity_ramp_duration
+      );
+      self.gravity_ramp_duration = 0.;
+    }
+    if !(0. ..=1.).contains(&self.gravity_ramp_start) {
+      warn!(
+        "gravity_ramp_start of {} isn't a fraction, it's clamped to 0 to 1.",
+        self.gravity_ramp_start
+      );
+      self.gravity_ramp_start = self.gravity_ramp_start.max(0.).min(1.);
+    }
+    self
+  }
+
+  /// Share of `BIRD_GRAVITY` pulling on a bird which has been flying for `flight_time`.
+  fn gravity_scale(&self, flight_time: f32) -> f32 {
+    if self.gravity_ramp_duration <= 0. {
+      return 1.;
+    }
+    let progress = (flight_time / self.gravity_ramp_duration).min(1.);
+    self.gravity_ramp_start + (1. - self.gravity_ramp_start) * progress
+  }
+
+  /// Flap impulse for a bird moving at `dy`, as if gravity pulled down. It stays between
+  /// `FLAP_SCALE_MIN` and `JUMP_ADD_MAX` times `BIRD_JUMP`.
+  fn flap_impulse(&self, dy: f32) -> f32 {
+    let falling = (-dy / BIRD_JUMP).max(-1.).min(1.);
+    (BIRD_JUMP * (1. + self.flap_fall_scaling * falling))
+      .max(BIRD_JUMP * FLAP_SCALE_MIN)
+      .min(BIRD_JUMP * JUMP_ADD_MAX)
+  }
+
+  /// Sorts a pass by the `margin` between the bird and the nearer pipe of the pair.
+  fn classify_pass(&self, margin: f32) -> PassKind {
+    if margin < self.near_miss_margin {
+      PassKind::Tight
+    } else if margin > self.wide_pass_margin {
+      PassKind::Wide
+    } else {
+      PassKind::Normal
+    }
+  }
+
+  /// Rectangle a pipe centered at `x`, `y` kills the bird in, `pipe_collision_width` wide
+  /// instead of the width of its sprite.
+  fn pipe_hitbox(&self, x: f32, y: f32) -> (f32, f32, f32, f32) {
+    (
+      x - self.pipe_collision_width / 2.,
+      y - PIPE_HEIGHT / 2.,
+      x + self.pipe_collision_width / 2.,
+      y + PIPE_HEIGHT / 2.,
+    )
+  }
+
+  /// Rectangle the ground centered at `x`, `y` kills the bird in, its top lowered by
+  /// `ground_collision_tolerance`.
+  fn ground_hitbox(&self, x: f32, y: f32) -> (f32, f32, f32, f32) {
+    (
+      x - GROUND_WIDTH / 2.,
+      y - GROUND_HEIGHT / 2.,
+      x + GROUND_WIDTH / 2.,
+      y + GROUND_HEIGHT / 2. - self.ground_collision_tolerance,
+    )
+  }
+
+  /// Pipe x, as if the world scrolled to the left, below which a pipe is out of sight for good.
+  fn pipe_despawn_x(&self, camera_zoom: f32) -> f32 {
+    (VIRTUAL_WIDTH / camera_zoom) / -2. - PIPE_WIDTH / 2. - self.despawn_margin
+  }
+
+  /// Substeps of the next physics step for a bird moving `dy` pixels per step.
+  fn substeps_for(&self, dy: f32) -> u32 {
+    if dy.abs() > self.substep_speed {
+      self.physics_substeps.max(1)
+    } else {
+      1
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+enum TextureFilter {
+  Nearest,
+  Linear,
+}
+
+impl From<TextureFilter> for Filter {
+  fn from(filter: TextureFilter) -> Self {
+    match filter {
+      TextureFilter::Nearest => Filter::Nearest,
+      TextureFilter::Linear => Filter::Linear,
+    }
+  }
+}
+
+impl Default for GameConfig {
+  fn default() -> Self {
+    GameConfig {
+      hurry_up_threshold: 60.,
+      hurry_up_rate: 0.01,
+      hurry_up_max: 0.5,
+      ground_collision_tolerance: 0.,
+      run_log_enabled: false,
+      run_log_path: "assets/logs/runs.jsonl".to_string(),
+      pipe_collision_width: PIPE_WIDTH,
+      death_replay_enabled: false,
+      texture_filter: TextureFilter::Linear,
+      start_dy: 0.,
+      pipe_whoosh_enabled: true,
+      combo_break_sound: true,
+      combo_timeout: Some(5.),
+      scroll_direction: ScrollDirection::Left,
+      pipe_frames: PipeFrames::First,
+      start_grace_period: 1.5,
+      power_ups_enabled: false,
+      pipe_wave_count: 1,
+      pipe_wave_spacing: 160.,
+      rhythm_bpm: 100.,
+      rhythm_tick_sound: true,
+      clear_color: (0., 0., 0., 1.),
+      flash_enabled: true,
+      flash_color: (1., 1., 1.),
+      flash_peak_alpha: 0.8,
+      flash_decay: 0.3,
+      impact_shake_scale: 0.5,
+      impact_shake_max: 6.,
+      jump_mode: JumpMode::Set,
+      despawn_margin: PIPE_WIDTH / 2.,
+      coin_chance: 0.3,
+      ceiling_wrap: false,
+      spatial_audio: false,
+      splash_duration: 2.,
+      reachability_check: true,
+      death_fall: true,
+      score_format: ScoreFormat::Plain,
+      jump_sounds: vec![JUMP_SOUND.to_string()],
+      max_score: None,
+      shuffle_music: false,
+      adaptive_volume: false,
+      first_pipe_delay: 3.,
+      game_over_timeout: None,
+      auto_share_card: false,
+      pipe_entry_duration: 0.4,
+      pipe_entry_collision: false,
+      stick_dead_zone: 0.15,
+      stick_flap_threshold: 0.5,
+      level: None,
+      death_sounds: false,
+      rewind_enabled: false,
+      hud: HudLayout::default(),
+      gravity_ramp_duration: 0.,
+      gravity_ramp_start: 0.5,
+      beginner_hints: false,
+      pipe_gap_min: PIPE_GAP,
+      pipe_gap_max: PIPE_GAP,
+      flap_trigger: FlapTrigger::Press,
+      bird_collision_radius: None,
+      pause_on_disconnect: true,
+      physics_substeps: 1,
+      substep_speed: 12.,
+      milestone_interval: Some(10),
+      milestone_effects: MilestoneEffects::default(),
+      time_scale_keys: false,
+      near_miss_margin: COMBO_MARGIN,
+      wide_pass_margin: WIDE_PASS_MARGIN,
+      flap_fall_scaling: 0.,
+      pause_music: false,
+      sprint_pipes: 50,
+      text_shadow: Some(TextShadowStyle {
+        offset: (3., -3.),
+        color: (0., 0., 0., 0.6),
+      }),
+      score_style: ScoreStyle::Font,
+      scored_pipe_tint: None,
+      confirm_quit: true,
+      gravity_wells: None,
+      title_bob_amplitude: TITLE_BIRD_AMPLITUDE,
+      title_bob_speed: TITLE_BOB_SPEED,
+    }
+  }
+}
+
+#[derive(Debug, Default)]
+struct RunStats {
+  survival_time: f32,
+  coins: u32,
+  /// Set once `NoClip` was turned on, such a run doesn't count towards the `Records`.
+  noclip_used: bool,
+  /// Pipe pairs the bird flew through.
+  pipes_passed: u32,
+  /// Set once a `GameMode::Sprint` run passed `GameConfig::sprint_pipes` pairs.
+  completed: bool,
+}
+
+/// Seconds left at the start of a run in which pipes can't kill the bird.
+#[derive(Debug, Default)]
+struct StartGrace {
+  remaining: f32,
+}
+
+impl RunStats {
+  /// Returns `0` until the hurry up threshold is reached, then grows up to `hurry_up_max`.
+  fn hurry_up(&self, config: &GameConfig) -> f32 {
+    ((self.survival_time - config.hurry_up_threshold) * config.hurry_up_rate)
+      .max(0.)
+      .min(config.hurry_up_max)
+  }
+}
+
+/// Loads a sprite sheet from a texture packer JSON atlas, supporting both the array and the
+/// hash flavour of the `frames` field.
+#[derive(Clone, Debug)]
+struct JsonSpriteSheetFormat(Handle<Texture>);
+
+#[derive(Debug, Deserialize)]
+struct JsonAtlas {
+  frames: JsonFrames,
+  meta: JsonMeta,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonFrames {
+  Array(Vec<JsonFrame>),
+  Hash(BTreeMap<String, JsonFrame>),
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonFrame {
+  frame: JsonRect,
+  #[serde(default)]
+  rotated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonRect {
+  x: u32,
+  y: u32,
+  w: u32,
+  h: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonMeta {
+  size: JsonSize,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonSize {
+  w: u32,
+  h: u32,
+}
+
+impl Format<SpriteSheet> for JsonSpriteSheetFormat {
+  fn name(&self) -> &'static str {
+    "JSON_SPRITE_SHEET"
+  }
+
+  fn import_simple(&self, bytes: Vec<u8>) -> amethyst::Result<SpriteSheet> {
+    Ok(SpriteSheet {
+      texture: self.0.clone(),
+      sprites: JsonSpriteSheetFormat::sprites(&bytes)?,
+    })
+  }
+}
+
+impl JsonSpriteSheetFormat {
+  /// Sprites of an atlas, the frames of the hash flavour are ordered by their name.
+  fn sprites(bytes: &[u8]) -> amethyst::Result<Vec<Sprite>> {
+    let atlas: JsonAtlas = serde_json::from_slice(bytes)?;
+    let frames = match atlas.frames {
+      JsonFrames::Array(frames) => frames,
+      JsonFrames::Hash(frames) => frames.into_iter().map(|(_, frame)| frame).collect(),
+    };
+
+    frames
+      .iter()
+      .map(|frame| {
+        if frame.rotated {
+          return Err(amethyst::Error::from_string(
+            "Rotated frames in JSON sprite sheets are not supported!",
+          ));
+        }
+        Ok(Sprite::from_pixel_values(
+          atlas.meta.size.w,
+          atlas.meta.size.h,
+          frame.frame.w,
+          frame.frame.h,
+          frame.frame.x,
+          frame.frame.y,
+          [0., 0.],
+          false,
+          false,
+        ))
+      })
+      .collect()
+  }
+}
+
+struct BackgroundSystem;
+
+impl<'a> System<'a> for BackgroundSystem {
+  type SystemData = (
+    WriteStorage<'a, Background>,
+    WriteStorage<'a, Transform>,
+    ReadStorage<'a, SpriteRender>,
+    Read<'a, AssetStorage<SpriteSheet>>,
+    Read<'a, Time>,
+    Read<'a, RunStats>,
+    Read<'a, GameConfig>,
+  );
+
+  fn run(
+    &mut self,
+    (mut backgrounds, mut transforms, sprites, sheets, time, run_stats, config): Self::SystemData,
+  ) {
+    for (background, sprite) in (&mut backgrounds, &sprites).join() {
+      if background.validated {
+        continue;
+      }
+      if let Some(width) = sheets
+        .get(&sprite.sprite_sheet)
+        .and_then(|sheet| sheet.sprites.get(sprite.sprite_number))
+        .map(|sprite| sprite.width)
+      {
+        let looping_point = background.b_type.looping_point();
+        if !covers_screen_while_looping(width, looping_point, BACKGROUND_LOOPING_OFFSET) {
+          warn!(
+            "{:?} texture is {} wide and won't loop seamless at {} with offset {}!",
+            background.b_type, width, looping_point, BACKGROUND_LOOPING_OFFSET
+          );
+        }
+        background.validated = true;
+      }
+    }
+
+    let speed_factor = 1. + run_stats.hurry_up(&config);
+    let sign = config.scroll_direction.sign();
+    for (background, transform) in (&mut backgrounds, &mut transforms).join() {
+      match background.b_type {
+        BackgroundType::Background => {
+          background.scroll_pos = (background.scroll_pos
+            + BACKGROUND_SCROLL_SPEED * speed_factor * time.delta_seconds())
+            % BACKGROUND_LOOPING_POINT;
+          transform.set_translation_x(sign * (BACKGROUND_LOOPING_OFFSET - background.scroll_pos));
+        }
+        BackgroundType::Ground => {
+          background.scroll_pos = (background.scroll_pos
+            + GROUND_SCROLL_SPEED * speed_factor * time.delta_seconds())
+            % GROUND_LOOPING_POINT;
+          transform.set_translation_x(sign * (BACKGROUND_LOOPING_OFFSET - background.scroll_pos));
+        }
+      }
+    }
+  }
+}
+
+/// Scales `Time::delta_seconds`, which the physics clock and every other game timer run on.
+#[derive(Default)]
+struct TimeScaleSystem {
+  slower_pressed: bool,
+  faster_pressed: bool,
+}
+
+impl<'a> System<'a> for TimeScaleSystem {
+  type SystemData = (
+    Write<'a, Time>,
+    Read<'a, InputHandler<StringBindings>>,
+    Read<'a, GameConfig>,
+  );
+
+  fn run(&mut self, (mut time, input, config): Self::SystemData) {
+    let slower_pressed = input.key_is_down(VirtualKeyCode::LBracket);
+    let faster_pressed = input.key_is_down(VirtualKeyCode::RBracket);
+    let slower = slower_pressed && slower_pressed != self.slower_pressed;
+    let faster = faster_pressed && faster_pressed != self.faster_pressed;
+    self.slower_pressed = slower_pressed;
+    self.faster_pressed = faster_pressed;
+    if !config.time_scale_keys || slower == faster {
+      return;
+    }
+
+    let current = TIME_SCALES
+      .iter()
+      .position(|scale| (scale - time.time_scale()).abs() < std::f32::EPSILON)
+      .unwrap_or(2);
+    let next = if faster {
+      (current + 1).min(TIME_SCALES.len() - 1)
+    } else {
+      current.saturating_sub(1)
+    };
+    time.set_time_scale(TIME_SCALES[next]);
+    info!("Simulation speed set to {}x.", TIME_SCALES[next]);
+  }
+}
+
+#[derive(Default)]
+struct CameraZoomSystem {
+  zoom_in_pressed: bool,
+  zoom_out_pressed: bool,
+}
+
+impl<'a> System<'a> for CameraZoomSystem {
+  type SystemData = (
+    WriteStorage<'a, Camera>,
+    Read<'a, InputHandler<StringBindings>>,
+    Write<'a, Settings>,
+  );
+
+  fn run(&mut self, (mut cameras, input, mut settings): Self::SystemData) {
+    let zoom_in_pressed =
+      input.key_is_down(VirtualKeyCode::Equals) || input.key_is_down(VirtualKeyCode::Add);
+    let zoom_out_pressed =
+      input.key_is_down(VirtualKeyCode::Minus) || input.key_is_down(VirtualKeyCode::Subtract);
+
+    let mut zoom = settings.camera_zoom;
+    if zoom_in_pressed && zoom_in_pressed != self.zoom_in_pressed {
+      zoom += CAMERA_ZOOM_STEP;
+    }
+    if zoom_out_pressed && zoom_out_pressed != self.zoom_out_pressed {
+      zoom -= CAMERA_ZOOM_STEP;
+    }
+    self.zoom_in_pressed = zoom_in_pressed;
+    self.zoom_out_pressed = zoom_out_pressed;
+
+    let zoom = zoom.max(MIN_CAMERA_ZOOM).min(MAX_CAMERA_ZOOM);
+    if (zoom - settings.camera_zoom).abs() > std::f32::EPSILON {
+      settings.camera_zoom = zoom;
+      save_settings(&settings);
+      for camera in (&mut cameras).join() {
+        *camera = zoomed_camera(zoom);
+      }
+    }
+  }
+}
+
+#[derive(Default)]
+struct DebugOverlaySystem {
+  toggle_pressed: bool,
+}
+
+impl<'a> System<'a> for DebugOverlaySystem {
+  type SystemData = (
+    WriteStorage<'a, UiText>,
+    WriteStorage<'a, Hidden>,
+    Read<'a, InputHandler<StringBindings>>,
+    Read<'a, FpsCounter>,
+    Read<'a, PhysicsClock>,
+    Read<'a, Time>,
+    Option<ReadExpect<'a, DebugOverlay>>,
+  );
+
+  fn run(
+    &mut self,
+    (mut ui_texts, mut hidden, input, fps_counter, clock, time, overlay): Self::SystemData,
+  ) {
+    let overlay = match overlay {
+      Some(overlay) => overlay,
+      None => return,
+    };
+
+    let toggle_pressed = input.key_is_down(VirtualKeyCode::F3);
+    if toggle_pressed && toggle_pressed != self.toggle_pressed {
+      if hidden.contains(overlay.text) {
+        hidden.remove(overlay.text);
+      } else {
+        hidden
+          .insert(overlay.text, Hidden)
+          .expect("Error while trying to hide debug overlay!");
+      }
+    }
+    self.toggle_pressed = toggle_pressed;
+
+    if hidden.contains(overlay.text) {
+      return;
+    }
+    if let Some(text) = ui_texts.get_mut(overlay.text) {
+      text.text = format!(
+        "FPS: {:.0}  Physics: {:.0} Hz, {} step(s) this frame  Speed: {}x{}",
+        fps_counter.sampled_fps(),
+        1. / PHYSICS_STEP,
+        clock.steps,
+        time.time_scale(),
+        if clock.lagging {
+          "  FALLING BEHIND!"
+        } else {
+          ""
+        },
+      );
+    }
+  }
+}
+
+#[derive(Default)]
+struct ProfilerOverlaySystem {
+  toggle_pressed: bool,
+}
+
+impl<'a> System<'a> for ProfilerOverlaySystem {
+  type SystemData = (
+    WriteStorage<'a, UiText>,
+    WriteStorage<'a, Hidden>,
+    Read<'a, InputHandler<StringBindings>>,
+    Option<ReadExpect<'a, Profiler>>,
+  );
+
+  fn run(&mut self, (mut ui_texts, mut hidden, input, profiler): Self::SystemData) {
+    let profiler = match profiler {
+      Some(profiler) => profiler,
+      None => return,
+    };
+
+    let toggle_pressed = input.key_is_down(VirtualKeyCode::F4);
+    if toggle_pressed && toggle_pressed != self.toggle_pressed {
+      if hidden.contains(profiler.text) {
+        hidden.remove(profiler.text);
+        profiler.enabled.store(true, Ordering::Relaxed);
+      } else {
+        hidden
+          .insert(profiler.text, Hidden)
+          .expect("Error while trying to hide profiler overlay!");
+        profiler.enabled.store(false, Ordering::Relaxed);
+      }
+    }
+    self.toggle_pressed = toggle_pressed;
+
+    if hidden.contains(profiler.text) {
+      return;
+    }
+    if let Some(text) = ui_texts.get_mut(profiler.text) {
+      text.text = if profiler.timings.is_empty() {
+        "No play systems are running".to_string()
+      } else {
+        profiler
+          .timings
+          .iter()
+          .map(|(name, average)| {
+            let average = average.lock().map_or(0., |average| *average);
+            format!("{}: {:.1} us", name, average)
+          })
+          .collect::<Vec<_>>()
+          .join("\n")
+      };
+    }
+  }
+}
+
+/// Recreates the widgets of `UI_LAYOUT` on `F7` so layout tweaks show up without a restart. The
+/// texts come back the way they are authored, e.g. a hidden title is shown again.
+#[derive(Default)]
+struct UiReloadSystem {
+  reload_pressed: bool,
+}
+
+impl<'a> System<'a> for UiReloadSystem {
+  type SystemData = (
+    Entities<'a>,
+    UiCreator<'a>,
+    ReadExpect<'a, ParentHierarchy>,
+    Option<WriteExpect<'a, UiRoot>>,
+    Read<'a, InputHandler<StringBindings>>,
+  );
+
+  fn run(&mut self, (entities, mut creator, hierarchy, root, input): Self::SystemData) {
+    let reload_pressed = input.key_is_down(VirtualKeyCode::F7);
+    let reload = reload_pressed && reload_pressed != self.reload_pressed;
+    self.reload_pressed = reload_pressed;
+
+    let mut root = match root {
+      Some(root) if reload => root,
+      _ => return,
+    };
+    // The old widgets stay around unless the new layout at least parses.
+    if let Err(e) = read_ui_layout() {
+      warn!(
+        "Keeping the current UI, {} can't be reloaded: {}",
+        UI_LAYOUT, e
+      );
+      return;
+    }
+
+    for e in hierarchy.all_children_iter(root.entity) {
+      entities
+        .delete(e)
+        .expect("Error while removing an old UI widget!");
+    }
+    entities
+      .delete(root.entity)
+      .expect("Error while removing the old UI root!");
+    root.entity = creator.create(UI_LAYOUT, ());
+  }
+}
+
+/// Widgets which were already hidden are left alone so they stay hidden once the UI is back.
+#[derive(Default)]
+struct HideUiSystem {
+  toggle_pressed: bool,
+  hidden: Vec<Entity>,
+}
+
+impl<'a> System<'a> for HideUiSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, UiTransform>,
+    WriteStorage<'a, Hidden>,
+    Write<'a, HideUi>,
+    Read<'a, InputHandler<StringBindings>>,
+  );
+
+  fn run(&mut self, (entities, ui_transforms, mut hidden, mut hide_ui, input): Self::SystemData) {
+    let toggle_pressed = input.key_is_down(VirtualKeyCode::F9);
+    if toggle_pressed && toggle_pressed != self.toggle_pressed {
+      hide_ui.hide = !hide_ui.hide;
+    }
+    self.toggle_pressed = toggle_pressed;
+
+    if hide_ui.hide {
+      // Also catches widgets which are created while the UI is hidden.
+      let visible: Vec<Entity> = (&entities, &ui_transforms, !&hidden)
+        .join()
+        .map(|(e, _, _)| e)
+        .collect();
+      for e in visible {
+        hidden
+          .insert(e, Hidden)
+          .expect("Error while trying to hide the UI!");
+        self.hidden.push(e);
+      }
+    } else {
+      for e in self.hidden.drain(..) {
+        hidden.remove(e);
+      }
+    }
+  }
+}
+
+struct UiScaleSystem;
+
+impl<'a> System<'a> for UiScaleSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadExpect<'a, ScreenDimensions>,
+    Write<'a, UiScale>,
+    WriteStorage<'a, UiBaseLayout>,
+    WriteStorage<'a, UiText>,
+    WriteStorage<'a, UiTransform>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, dimensions, mut scale, mut layouts, mut texts, mut transforms): Self::SystemData,
+  ) {
+    let factor = (dimensions.width() / UI_REFERENCE_WIDTH)
+      .min(dimensions.height() / UI_REFERENCE_HEIGHT)
+      .max(std::f32::EPSILON);
+    let rescale_all = (factor - scale.factor).abs() > std::f32::EPSILON;
+    scale.factor = factor;
+
+    let mut unscaled = Vec::new();
+    for (e, text, transform, _) in (&entities, &texts, &transforms, !&layouts).join() {
+      unscaled.push((
+        e,
+        UiBaseLayout {
+          font_size: text.font_size,
+          x: transform.local_x,
+          y: transform.local_y,
+          width: transform.width,
+          height: transform.height,
+        },
+      ));
+    }
+    let mut entities_to_scale: Vec<Entity> = unscaled.iter().map(|(e, _)| *e).collect();
+    for (e, layout) in unscaled {
+      layouts
+        .insert(e, layout)
+        .expect("Error while trying to remember a UI layout!");
+    }
+    if rescale_all {
+      entities_to_scale = (&entities, &layouts).join().map(|(e, _)| e).collect();
+    }
+
+    for e in entities_to_scale {
+      if let (Some(layout), Some(text), Some(transform)) =
+        (layouts.get(e), texts.get_mut(e), transforms.get_mut(e))
+      {
+        text.font_size = layout.font_size * factor;
+        transform.local_x = layout.x * factor;
+        transform.local_y = layout.y * factor;
+        transform.width = layout.width * factor;
+        transform.height = layout.height * factor;
+      }
+    }
+  }
+}
+
+/// Keeps every shadow in sync with its source text and deletes the ones whose source is gone.
+struct TextShadowSystem;
+
+impl<'a> System<'a> for TextShadowSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, TextShadow>,
+    WriteStorage<'a, UiText>,
+    WriteStorage<'a, UiTransform>,
+    WriteStorage<'a, Hidden>,
+    Read<'a, UiScale>,
+    Read<'a, GameConfig>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, shadows, mut texts, mut transforms, mut hidden, scale, config): Self::SystemData,
+  ) {
+    let style = match config.text_shadow {
+      Some(style) => style,
+      None => return,
+    };
+    let (r, g, b, a) = style.color;
+    for (e, shadow) in (&entities, &shadows).join() {
+      let source = match (texts.get(shadow.source), transforms.get(shadow.source)) {
+        (Some(text), Some(transform)) if entities.is_alive(shadow.source) => (
+          text.text.clone(),
+          text.font_size,
+          text.color[3],
+          transform.local_x,
+          transform.local_y,
+          transform.local_z,
+          transform.width,
+          transform.height,
+        ),
+        _ => {
+          entities
+            .delete(e)
+            .expect("Error while trying to delete a text shadow!");
+          continue;
+        }
+      };
+      let (content, font_size, alpha, x, y, z, width, height) = source;
+      if let Some(text) = texts.get_mut(e) {
+        if text.text != content {
+          text.text = content;
+        }
+        text.font_size = font_size;
+        text.color = [r, g, b, a * alpha];
+      }
+      if let Some(transform) = transforms.get_mut(e) {
+        transform.local_x = x + style.offset.0 * scale.factor;
+        transform.local_y = y + style.offset.1 * scale.factor;
+        transform.local_z = z - 0.5;
+        transform.width = width;
+        transform.height = height;
+      }
+      if hidden.contains(shadow.source) {
+        hidden
+          .insert(e, Hidden)
+          .expect("Error while trying to hide a text shadow!");
+      } else {
+        hidden.remove(e);
+      }
+    }
+  }
+}
+
+struct BirdSystem;
+
+impl<'a> System<'a> for BirdSystem {
+  type SystemData = (
+    Entities<'a>,
+    WriteStorage<'a, Bird>,
+    ReadStorage<'a, Pipe>,
+    ReadStorage<'a, GravityWell>,
+    WriteStorage<'a, Transform>,
+    Read<'a, InputHandler<StringBindings>>,
+    Read<'a, Settings>,
+    Read<'a, GameMode>,
+    ReadExpect<'a, GravityFlip>,
+    Read<'a, LazyUpdate>,
+    Option<ReadExpect<'a, ParticleSprite>>,
+    Read<'a, AssetStorage<Source>>,
+    Option<ReadExpect<'a, Sounds>>,
+    Option<Read<'a, Output>>,
+    ReadExpect<'a, GameConfig>,
+    WriteStorage<'a, AudioEmitter>,
+    Read<'a, PhysicsClock>,
+  );
+
+  fn run(
+    &mut self,
+    (
+      entities,
+      mut birds,
+      pipes,
+      gravity_wells,
+      mut transforms,
+      input,
+      settings,
+      mode,
+      gravity_flip,
+      lazy,
+      particle_sprite,
+      storage,
+      sounds,
+      output,
+      config,
+      mut emitters,
+      clock,
+    ): Self::SystemData,
+  ) {
+    // Forces act over the substep, a flap and a bounce only happen once per step.
+    let substeps = clock.substeps.max(1) as f32;
+    let step = PHYSICS_STEP / substeps;
+    let first_substep = clock.substep == 0;
+    let gravity_sign = if gravity_flip.remaining > 0. { -1. } else { 1. };
+    let assist = settings.assist_strength > 0. && *mode != GameMode::Tutorial;
+    let sign = config.scroll_direction.sign();
+    let gaps = if assist {
+      gap_centers(&pipes, &transforms, sign)
+    } else {
+      Vec::new()
+    };
+    let wells: Vec<(Vector3<f32>, f32, f32)> = (&gravity_wells, &transforms)
+      .join()
+      .map(|(well, transform)| (*transform.translation(), well.radius, well.strength))
+      .collect();
+    for (bird, transform, emitter) in (&mut birds, &mut transforms, (&mut emitters).maybe()).join()
+    {
+      bird.dy += gravity_sign * BIRD_GRAVITY * config.gravity_scale(bird.flight_time) * step;
+      for (center, radius, strength) in wells.iter() {
+        let dx = center.x - transform.translation().x;
+        let dy = center.y - transform.translation().y;
+        let distance = (dx * dx + dy * dy).sqrt();
+        if distance < *radius && distance > std::f32::EPSILON {
+          // The bird never moves sideways, so only the vertical part of the pull counts.
+          bird.dy += strength * (dy / distance) * (1. - distance / radius) * step;
+        }
+      }
+      bird.flight_time += step;
+      if !first_substep {
+        transform.prepend_translation_y(bird.dy / substeps);
+        wrap_or_clamp(bird, transform, &config, *mode);
+        continue;
+      }
+      bird.stick_down = stick_flap_down(
+        input.axis_value(FLAP_AXIS).unwrap_or(0.),
+        bird.stick_down,
+        config.stick_dead_zone,
+        config.stick_flap_threshold,
+      );
+      let manual_flap = bird.flap_triggered(
+        is_primary_action_down(&input, settings.one_button_mode) || bird.stick_down,
+        config.flap_trigger,
+      );
+      bird.assist_idle = if manual_flap {
+        0.
+      } else {
+        bird.assist_idle + PHYSICS_STEP
+      };
+      let assisted_flap = assist && bird.assist_idle >= ASSIST_OVERRIDE && {
+        // The next pair is the closest one the bird hasn't fully passed yet.
+        let bird_x = sign * transform.translation().x;
+        let target_y = gaps
+          .iter()
+          .filter(|(x, _)| x + PIPE_WIDTH / 2. > bird_x - BIRD_WIDTH / 2.)
+          .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+          .map_or(0., |(_, center)| *center);
+        assist_wants_flap(
+          transform.translation().y,
+          bird.dy,
+          target_y,
+          gravity_sign,
+          settings.assist_strength,
+        )
+      };
+      if manual_flap || assisted_flap {
+        // Velocities are compared as if gravity pulled down, so the cap holds while flipped.
+        let jump = config.flap_impulse(gravity_sign * bird.dy);
+        match config.jump_mode {
+          JumpMode::Set => bird.dy = gravity_sign * jump,
+          JumpMode::Add => {
+            let dy = (gravity_sign * bird.dy + jump).min(BIRD_JUMP * JUMP_ADD_MAX);
+            bird.dy = gravity_sign * dy;
+          }
+          JumpMode::Eased => bird.jump_ease = JUMP_EASE_STEPS,
+        }
+        // Both are loaded with the assets, a world without them still flies, e.g. in tests.
+        if let Some(sounds) = sounds.as_deref() {
+          play_jump_sound(sounds, emitter, &storage, output.as_deref());
+        }
+        if let Some(particle_sprite) = particle_sprite.as_deref() {
+          for &(dx, dy) in FLAP_PUFF_VELOCITIES {
+            let mut puff_transform = Transform::from(Vector3::new(
+              transform.translation().x,
+              transform.translation().y - BIRD_HEIGHT / 2.,
+              Z_PARTICLE,
+            ));
+            puff_transform.set_scale(Vector3::new(1.5, 1.5, 1.));
+            lazy
+              .create_entity(&entities)
+              .with(Particle {
+                dx,
+                dy,
+                age: 0.,
+                lifetime: FLAP_PUFF_LIFETIME,
+              })
+              .with(particle_sprite.sprite.clone())
+              .with(puff_transform)
+              .with(Tint(Srgba::new(1., 1., 1., 1.)))
+              .with(Transparent)
+              .build();
+          }
+        }
+      }
+
+      if bird.jump_ease > 0 {
+        bird.dy += (gravity_sign * BIRD_JUMP - bird.dy) / bird.jump_ease as f32;
+        bird.jump_ease -= 1;
+      }
+
+      if *mode == GameMode::Bounce {
+        bird.bounce_grace = (bird.bounce_grace - PHYSICS_STEP).max(0.);
+        if let Some(separation) = bird.bounce.take() {
+          transform.prepend_translation(separation);
+          bird.dy = if separation.y.abs() > std::f32::EPSILON {
+            separation.y.signum() * bird.dy.abs().max(BIRD_JUMP) * BOUNCE_DAMPING
+          } else {
+            bird.dy * BOUNCE_DAMPING
+          };
+          bird.bounce_grace = BOUNCE_GRACE;
+          bird.jump_ease = 0;
+        }
+        // A bounce off the front of a pipe pushes the bird back, it drifts home afterwards.
+        let x = transform.translation().x;
+        transform.set_translation_x(x * 0.95);
+      }
+
+      transform.prepend_translation_y(bird.dy / substeps);
+      wrap_or_clamp(bird, transform, &config, *mode);
+    }
+  }
+}
+
+/// Brings a bird which flew off the top back in at the bottom with `GameConfig::ceiling_wrap`, or
+/// keeps it on the screen in the modes without a ground.
+fn wrap_or_clamp(bird: &mut Bird, transform: &mut Transform, config: &GameConfig, mode: GameMode) {
+  // Pipes reach past the top of the screen, so the bird can only wrap between two pairs. A
+  // bottom pipe right where it comes back in still kills it.
+  let clamped = mode == GameMode::Zen || mode == GameMode::Tutorial;
+  if config.ceiling_wrap && !clamped {
+    let y = transform.translation().y;
+    if above_ceiling(y) {
+      transform.set_translation_y(VIRTUAL_HEIGHT / -2. + GROUND_HEIGHT + BIRD_HEIGHT / 2.);
+    }
+  }
+
+  if clamped {
+    let ceiling = VIRTUAL_HEIGHT / 2. - BIRD_HEIGHT / 2.;
+    let floor = VIRTUAL_HEIGHT / -2. + GROUND_HEIGHT + BIRD_HEIGHT / 2.;
+    let y = transform.translation().y;
+    if y > ceiling || y < floor {
+      transform.set_translation_y(y.max(floor).min(ceiling));
+      bird.dy = 0.;
+    }
+  }
+}
+
+struct ScoreDigitsSystem;
+
+impl<'a> System<'a> for ScoreDigitsSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, UiText>,
+    ReadStorage<'a, Hidden>,
+    WriteStorage<'a, SpriteRender>,
+    WriteStorage<'a, Transform>,
+    Option<ReadExpect<'a, Score>>,
+    Option<WriteExpect<'a, ScoreDigits>>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, ui_texts, hidden, mut sprites, mut transforms, score, digits): Self::SystemData,
+  ) {
+    let mut digits = match digits {
+      Some(digits) => digits,
+      None => return,
+    };
+    // A hidden score text hides the digits standing in for it as well.
+    let text = score
+      .filter(|score| !hidden.contains(score.text))
+      .and_then(|score| ui_texts.get(score.text))
+      .map_or(String::new(), |text| text.text.clone());
+    if text == digits.shown {
+      return;
+    }
+
+    for e in digits.digits.drain(..) {
+      entities
+        .delete(e)
+        .expect("Error while removing a score digit!");
+    }
+    let numbers: Vec<u32> = text.chars().filter_map(|c| c.to_digit(10)).collect();
+    let left = -(numbers.len() as f32 - 1.) * SCORE_DIGIT_WIDTH / 2.;
+    for (i, number) in numbers.into_iter().enumerate() {
+      let mut sprite = digits.sprite.clone();
+      sprite.sprite_number = number as usize;
+      let e = entities
+        .build_entity()
+        .with(sprite, &mut sprites)
+        .with(
+          Transform::from(Vector3::new(
+            left + i as f32 * SCORE_DIGIT_WIDTH,
+            VIRTUAL_HEIGHT / 2. - 20.,
+            Z_SCORE_DIGITS,
+          )),
+          &mut transforms,
+        )
+        .build();
+      digits.digits.push(e);
+    }
+    digits.shown = text;
+  }
+}
+
+struct FlashSystem;
+
+impl<'a> System<'a> for FlashSystem {
+  type SystemData = (
+    WriteStorage<'a, UiImage>,
+    Option<WriteExpect<'a, Flash>>,
+    Read<'a, GameConfig>,
+    Read<'a, Time>,
+  );
+
+  fn run(&mut self, (mut images, flash, config, time): Self::SystemData) {
+    let mut flash = match flash {
+      Some(flash) => flash,
+      None => return,
+    };
+    if flash.alpha <= 0. {
+      return;
+    }
+    let fade = config.flash_peak_alpha * time.delta_seconds() / config.flash_decay.max(0.01);
+    flash.alpha = (flash.alpha - fade).max(0.);
+    if let Some(image) = images.get_mut(flash.overlay) {
+      let (r, g, b) = config.flash_color;
+      *image = UiImage::SolidColor([r, g, b, flash.alpha]);
+    }
+  }
+}
+
+/// Throws the camera around while a `Shake` lasts, it calms down towards the end.
+struct ShakeSystem;
+
+impl<'a> System<'a> for ShakeSystem {
+  type SystemData = (
+    ReadStorage<'a, Camera>,
+    WriteStorage<'a, Transform>,
+    Write<'a, Shake>,
+    Read<'a, Time>,
+  );
+
+  fn run(&mut self, (cameras, mut transforms, mut shake, time): Self::SystemData) {
+    if shake.remaining <= 0. {
+      return;
+    }
+    shake.remaining = (shake.remaining - time.delta_seconds()).max(0.);
+    let strength = shake.magnitude * shake.remaining / SHAKE_DURATION;
+    // The last frame puts the camera back in place.
+    let (x, y) = if strength > 0. {
+      let mut rand = thread_rng();
+      (
+        rand.gen_range(-strength, strength),
+        rand.gen_range(-strength, strength),
+      )
+    } else {
+      (0., 0.)
+    };
+    for (_, transform) in (&cameras, &mut transforms).join() {
+      transform.set_translation_x(x);
+      transform.set_translation_y(y);
+    }
+  }
+}
+
+struct TitleBobSystem;
+
+impl<'a> System<'a> for TitleBobSystem {
+  type SystemData = (
+    WriteStorage<'a, TitleBob>,
+    WriteStorage<'a, Transform>,
+    Read<'a, Time>,
+    Read<'a, GameConfig>,
+  );
+
+  fn run(&mut self, (mut bobs, mut transforms, time, config): Self::SystemData) {
+    for (bob, transform) in (&mut bobs, &mut transforms).join() {
+      bob.phase =
+        (bob.phase + config.title_bob_speed * time.delta_seconds()) % (2. * std::f32::consts::PI);
+      transform.set_translation_y(bob.base_y + bob.amplitude * bob.phase.sin());
+    }
+  }
+}
+
+struct ParticleSystem;
+
+impl<'a> System<'a> for ParticleSystem {
+  type SystemData = (
+    Entities<'a>,
+    WriteStorage<'a, Particle>,
+    WriteStorage<'a, Transform>,
+    WriteStorage<'a, Tint>,
+  );
+
+  fn run(&mut self, (entities, mut particles, mut transforms, mut tints): Self::SystemData) {
+    for (e, particle, transform, tint) in
+      (&entities, &mut particles, &mut transforms, &mut tints).join()
+    {
+      particle.age += PHYSICS_STEP;
+      if particle.age >= particle.lifetime {
+        entities
+          .delete(e)
+          .expect("Error while removing non existing particle! This should never happened!");
+        continue;
+      }
+      transform.prepend_translation_x(particle.dx * PHYSICS_STEP);
+      transform.prepend_translation_y(particle.dy * PHYSICS_STEP);
+      tint.0.alpha = 1. - particle.age / particle.lifetime;
+    }
+  }
+}
+
+/// Scrolls everything with a `T` by one step and deletes what's out of sight for good, `reach` is
+/// how far it extends from its center, a pipe's is `PIPE_WIDTH / 2.`. Zooming out shows more of
+/// the world, so the edge follows the camera.
+fn scroll_and_despawn<T, D>(
+  entities: &Entities<'_>,
+  markers: &Storage<'_, T, D>,
+  transforms: &mut WriteStorage<'_, Transform>,
+  config: &GameConfig,
+  camera_zoom: f32,
+  speed_factor: f32,
+  reach: impl Fn(&T) -> f32,
+) where
+  T: Component,
+  D: Deref<Target = MaskedStorage<T>>,
+{
+  let sign = config.scroll_direction.sign();
+  let despawn_x = config.pipe_despawn_x(camera_zoom.max(MIN_CAMERA_ZOOM)) + PIPE_WIDTH / 2.;
+  for (e, marker, transform) in (entities, markers, transforms).join() {
+    transform.prepend_translation_x(sign * PIPE_SCROLL * speed_factor * PHYSICS_STEP);
+    if sign * transform.translation().x < despawn_x - reach(marker) {
+      entities
+        .delete(e)
+        .expect("Error while removing non existing entity! This should never happened!");
+    }
+  }
+}
+
+struct PipeSystem;
+
+impl<'a> System<'a> for PipeSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, Pipe>,
+    WriteStorage<'a, Transform>,
+    Read<'a, RunStats>,
+    Read<'a, GameConfig>,
+    Read<'a, Settings>,
+    Read<'a, GameMode>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, pipes, mut transforms, run_stats, config, settings, mode): Self::SystemData,
+  ) {
+    let speed_factor = if *mode == GameMode::Tutorial {
+      TUTORIAL_SCROLL_FACTOR
+    } else {
+      1. + run_stats.hurry_up(&config)
+    };
+    scroll_and_despawn(
+      &entities,
+      &pipes,
+      &mut transforms,
+      &config,
+      settings.camera_zoom,
+      speed_factor,
+      |_| PIPE_WIDTH / 2.,
+    );
+  }
+}
+
+/// The entry only starts once the pipe's leading edge is on screen, otherwise it would be over
+/// before anyone could see it.
+struct PipeEntrySystem;
+
+impl<'a> System<'a> for PipeEntrySystem {
+  type SystemData = (
+    Entities<'a>,
+    WriteStorage<'a, PipeEntry>,
+    WriteStorage<'a, Transform>,
+    Read<'a, GameConfig>,
+    Read<'a, Settings>,
+    Read<'a, GameMode>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, mut entries, mut transforms, config, settings, mode): Self::SystemData,
+  ) {
+    let sign = config.scroll_direction.sign();
+    // The rotated camera of a tube run shows the world's height from top to bottom.
+    let view = if *mode == GameMode::Tube {
+      VIRTUAL_HEIGHT
+    } else {
+      VIRTUAL_WIDTH
+    };
+    let view_edge = view / 2. / settings.camera_zoom.max(MIN_CAMERA_ZOOM);
+    let duration = config.pipe_entry_duration.max(PHYSICS_STEP);
+    let mut finished = Vec::new();
+    for (e, entry, transform) in (&entities, &mut entries, &mut transforms).join() {
+      if sign * transform.translation().x - PIPE_WIDTH / 2. < view_edge {
+        entry.elapsed += PHYSICS_STEP;
+      }
+      let t = (entry.elapsed / duration).min(1.);
+      // Eases out, the pipe slows down as it settles.
+      transform.set_translation_y(entry.target_y + entry.offset * (1. - t) * (1. - t));
+      if t >= 1. {
+        finished.push(e);
+      }
+    }
+    for e in finished {
+      entries.remove(e);
+    }
+  }
+}
+
+/// Scrolls the power-ups along with the pipes and runs down an active gravity flip.
+struct PowerUpSystem;
+
+impl<'a> System<'a> for PowerUpSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, PowerUp>,
+    WriteStorage<'a, Transform>,
+    WriteStorage<'a, UiText>,
+    WriteStorage<'a, Hidden>,
+    WriteExpect<'a, GravityFlip>,
+    Read<'a, RunStats>,
+    Read<'a, GameConfig>,
+    Read<'a, Settings>,
+  );
+
+  fn run(
+    &mut self,
+    (
+      entities,
+      power_ups,
+      mut transforms,
+      mut ui_texts,
+      mut hidden,
+      mut flip,
+      run_stats,
+      config,
+      settings,
+    ): Self::SystemData,
+  ) {
+    // A power-up leaves along with the pair it was spawned in.
+    scroll_and_despawn(
+      &entities,
+      &power_ups,
+      &mut transforms,
+      &config,
+      settings.camera_zoom,
+      1. + run_stats.hurry_up(&config),
+      |_| PIPE_WIDTH / 2.,
+    );
+
+    if flip.remaining > 0. {
+      flip.remaining = (flip.remaining - PHYSICS_STEP).max(0.);
+      if let Some(text) = ui_texts.get_mut(flip.text) {
+        text.text = format!("Gravity Flipped: {:.1}", flip.remaining);
+      }
+      hidden.remove(flip.text);
+    } else if !hidden.contains(flip.text) {
+      hidden
+        .insert(flip.text, Hidden)
+        .expect("Error while trying to hide the gravity flip timer!");
+    }
+  }
+}
+
+/// Scrolls the coins along with the pipes.
+struct CoinSystem;
+
+impl<'a> System<'a> for CoinSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, Coin>,
+    WriteStorage<'a, Transform>,
+    Read<'a, RunStats>,
+    Read<'a, GameConfig>,
+    Read<'a, Settings>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, coins, mut transforms, run_stats, config, settings): Self::SystemData,
+  ) {
+    // A coin leaves along with the pair it was spawned in.
+    scroll_and_despawn(
+      &entities,
+      &coins,
+      &mut transforms,
+      &config,
+      settings.camera_zoom,
+      1. + run_stats.hurry_up(&config),
+      |_| PIPE_WIDTH / 2.,
+    );
+  }
+}
+
+/// Scrolls the gravity wells along with the pipes and shrinks their rings.
+struct GravityWellSystem;
+
+impl<'a> System<'a> for GravityWellSystem {
+  type SystemData = (
+    Entities<'a>,
+    WriteStorage<'a, GravityWell>,
+    WriteStorage<'a, Transform>,
+    Read<'a, RunStats>,
+    Read<'a, GameConfig>,
+    Read<'a, Settings>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, mut wells, mut transforms, run_stats, config, settings): Self::SystemData,
+  ) {
+    scroll_and_despawn(
+      &entities,
+      &wells,
+      &mut transforms,
+      &config,
+      settings.camera_zoom,
+      1. + run_stats.hurry_up(&config),
+      |well| well.radius,
+    );
+    for (well, transform) in (&mut wells, &mut transforms).join() {
+      well.phase = (well.phase + PHYSICS_STEP / GRAVITY_WELL_PULSE) % 1.;
+      // The ring sprite is 32 pixels wide.
+      let scale = well.radius * 2. * (1. - well.phase) / 32.;
+      transform.set_scale(Vector3::new(scale, scale, 1.));
+    }
+  }
+}
+
+/// Runs once per frame instead of per physics step, the debug lines are only kept for a frame.
+/// The prediction steps through the same integration as the `BirdSystem`.
+struct TrajectorySystem;
+
+impl<'a> System<'a> for TrajectorySystem {
+  type SystemData = (
+    ReadStorage<'a, Bird>,
+    ReadStorage<'a, Transform>,
+    Read<'a, TrajectoryPreview>,
+    ReadExpect<'a, GravityFlip>,
+    Read<'a, RunStats>,
+    Read<'a, GameConfig>,
+    Option<Write<'a, DebugLines>>,
+  );
+
+  fn run(
+    &mut self,
+    (birds, transforms, preview, gravity_flip, run_stats, config, lines): Self::SystemData,
+  ) {
+    let mut lines = match lines {
+      Some(lines) if preview.shown && config.beginner_hints => lines,
+      _ => return,
+    };
+    // The bird stays put while the world scrolls, relative to the pipes it flies forward.
+    let advance = -config.scroll_direction.sign()
+      * PIPE_SCROLL
+      * (1. + run_stats.hurry_up(&config))
+      * PHYSICS_STEP;
+    for (bird, transform) in (&birds, &transforms).join() {
+      let mut position = Point3::new(
+        transform.translation().x,
+        transform.translation().y,
+        Z_PARTICLE,
+      );
+      let mut dy = bird.dy;
+      let mut jump_ease = bird.jump_ease;
+      for step in 0..(TRAJECTORY_DURATION / PHYSICS_STEP) as u32 {
+        let elapsed = step as f32 * PHYSICS_STEP;
+        let gravity_sign = if gravity_flip.remaining > elapsed {
+          -1.
+        } else {
+          1.
+        };
+        dy += gravity_sign
+          * BIRD_GRAVITY
+          * config.gravity_scale(bird.flight_time + elapsed)
+          * PHYSICS_STEP;
+        if jump_ease > 0 {
+          dy += (gravity_sign * BIRD_JUMP - dy) / jump_ease as f32;
+          jump_ease -= 1;
+        }
+        let next = Point3::new(position.x + advance, position.y + dy, position.z);
+        if (step / TRAJECTORY_DASH_STEPS) % 2 == 0 {
+          lines.draw_line(position, next, Srgba::new(1., 1., 1., 0.4));
+        }
+        position = next;
+      }
+    }
+  }
+}
+
+/// Outlines the bird and every pipe and ground hitbox while `NoClip` is on, per frame like the
+/// `TrajectorySystem`.
+struct HitboxSystem;
+
+impl<'a> System<'a> for HitboxSystem {
+  type SystemData = (
+    ReadStorage<'a, Bird>,
+    ReadStorage<'a, Pipe>,
+    ReadStorage<'a, Background>,
+    ReadStorage<'a, Transform>,
+    ReadExpect<'a, NoClip>,
+    Read<'a, GameConfig>,
+    Option<Write<'a, DebugLines>>,
+  );
+
+  fn run(
+    &mut self,
+    (birds, pipes, backgrounds, transforms, noclip, config, lines): Self::SystemData,
+  ) {
+    let mut lines = match lines {
+      Some(lines) if noclip.active => lines,
+      _ => return,
+    };
+    let color = Srgba::new(1., 0.2, 0.2, 1.);
+    for (_, transform) in (&pipes, &transforms).join() {
+      let (left, bottom, right, top) =
+        config.pipe_hitbox(transform.translation().x, transform.translation().y);
+      draw_outline(&mut lines, color, left, bottom, right, top);
+    }
+    for (background, transform) in (&backgrounds, &transforms).join() {
+      if let BackgroundType::Ground = background.b_type {
+        let (left, bottom, right, top) =
+          config.ground_hitbox(transform.translation().x, transform.translation().y);
+        draw_outline(&mut lines, color, left, bottom, right, top);
+      }
+    }
+    for (_, transform) in (&birds, &transforms).join() {
+      let x = transform.translation().x;
+      let y = transform.translation().y;
+      match config.bird_collision_radius {
+        Some(radius) => {
+          const SEGMENTS: u32 = 16;
+          let point = |i: u32| {
+            let angle = i as f32 / SEGMENTS as f32 * std::f32::consts::PI * 2.;
+            Point3::new(
+              x + radius * angle.cos(),
+              y + radius * angle.sin(),
+              Z_PARTICLE,
+            )
+          };
+          for i in 0..SEGMENTS {
+            lines.draw_line(point(i), point(i + 1), color);
+          }
+        }
+        None => draw_outline(
+          &mut lines,
+          color,
+          x - BIRD_WIDTH / 2.,
+          y - BIRD_HEIGHT / 2.,
+          x + BIRD_WIDTH / 2.,
+          y + BIRD_HEIGHT / 2.,
+        ),
+      }
+    }
+  }
+}
+
+/// Crosses out the `ImpactPoint` while the debug overlay is shown, the game over screen
+/// included.
+struct ImpactMarkerSystem;
+
+impl<'a> System<'a> for ImpactMarkerSystem {
+  type SystemData = (
+    ReadStorage<'a, Hidden>,
+    Read<'a, ImpactPoint>,
+    Option<ReadExpect<'a, DebugOverlay>>,
+    Option<Write<'a, DebugLines>>,
+  );
+
+  fn run(&mut self, (hidden, impact, overlay, lines): Self::SystemData) {
+    let (mut lines, (x, y)) = match (lines, overlay, impact.point) {
+      (Some(lines), Some(overlay), Some(point)) if !hidden.contains(overlay.text) => (lines, point),
+      _ => return,
+    };
+    let color = Srgba::new(1., 1., 0., 1.);
+    let size = IMPACT_MARKER_SIZE / 2.;
+    lines.draw_line(
+      Point3::new(x - size, y - size, Z_LOGO),
+      Point3::new(x + size, y + size, Z_LOGO),
+      color,
+    );
+    lines.draw_line(
+      Point3::new(x - size, y + size, Z_LOGO),
+      Point3::new(x + size, y - size, Z_LOGO),
+      color,
+    );
+  }
+}
+
+struct RhythmSystem;
+
+impl<'a> System<'a> for RhythmSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, Bird>,
+    WriteStorage<'a, Transform>,
+    WriteStorage<'a, SpriteRender>,
+    WriteStorage<'a, Tint>,
+    WriteStorage<'a, Transparent>,
+    WriteExpect<'a, RhythmGuide>,
+    Read<'a, Settings>,
+    Read<'a, GameConfig>,
+    Read<'a, RunStats>,
+    Read<'a, AssetStorage<Source>>,
+    ReadExpect<'a, Sounds>,
+    Option<Read<'a, Output>>,
+  );
+
+  fn run(
+    &mut self,
+    (
+      entities,
+      birds,
+      mut transforms,
+      mut sprites,
+      mut tints,
+      mut transparents,
+      mut guide,
+      settings,
+      config,
+      run_stats,
+      storage,
+      sounds,
+      output,
+    ): Self::SystemData,
+  ) {
+    let bird_position = (&birds, &transforms)
+      .join()
+      .next()
+      .map(|(_, transform)| *transform.translation());
+    let bird_position = match bird_position {
+      Some(position) if settings.rhythm_guide => position,
+      _ => {
+        if let Some(ring) = guide.ring.take() {
+          entities
+            .delete(ring)
+            .expect("Error while removing the rhythm ring!");
+        }
+        return;
+      }
+    };
+
+    let ring = match guide.ring {
+      Some(ring) => ring,
+      None => {
+        let ring = entities
+          .build_entity()
+          .with(guide.sprite.clone(), &mut sprites)
+          .with(Transform::default(), &mut transforms)
+          .with(Tint(Srgba::new(1., 1., 1., 1.)), &mut tints)
+          .with(Transparent, &mut transparents)
+          .build();
+        guide.ring.replace(ring);
+        ring
+      }
+    };
+
+    let bpm = config.rhythm_bpm.max(1.) * (1. + run_stats.hurry_up(&config));
+    guide.phase += PHYSICS_STEP * bpm / 60.;
+    if guide.phase >= 1. {
+      guide.phase %= 1.;
+      if config.rhythm_tick_sound {
+        play_tick_sound(&*sounds, &storage, output.as_deref());
+      }
+    }
+
+    if let Some(transform) = transforms.get_mut(ring) {
+      let scale = 1. + guide.phase;
+      transform.set_translation(Vector3::new(
+        bird_position.x,
+        bird_position.y,
+        Z_RHYTHM_RING,
+      ));
+      transform.set_scale(Vector3::new(scale, scale, 1.));
+    }
+    if let Some(tint) = tints.get_mut(ring) {
+      tint.0.alpha = 0.6 * (1. - guide.phase);
+    }
+  }
+}
+
+struct HurryUpSystem;
+
+impl<'a> System<'a> for HurryUpSystem {
+  type SystemData = (
+    Write<'a, RunStats>,
+    Read<'a, GameConfig>,
+    WriteStorage<'a, Hidden>,
+    ReadExpect<'a, HurryUpCue>,
+    Read<'a, AssetStorage<Source>>,
+    ReadExpect<'a, Sounds>,
+    Option<Read<'a, Output>>,
+  );
+
+  fn run(
+    &mut self,
+    (mut run_stats, config, mut hidden, cue, storage, sounds, output): Self::SystemData,
+  ) {
+    let was_hurrying = run_stats.survival_time > config.hurry_up_threshold;
+    run_stats.survival_time += PHYSICS_STEP;
+    let is_hurrying = run_stats.survival_time > config.hurry_up_threshold;
+
+    if is_hurrying && !was_hurrying {
+      hidden.remove(cue.text);
+      play_hurry_up_sound(&*sounds, &storage, output.as_deref());
+    }
+
+    if run_stats.survival_time > config.hurry_up_threshold + HURRY_UP_CUE_DURATION
+      && !hidden.contains(cue.text)
+    {
+      hidden
+        .insert(cue.text, Hidden)
+        .expect("Error while trying to hide hurry up cue!");
+    }
+  }
+}
+
+#[derive(Serialize)]
+struct RunRecord<'a> {
+  timestamp: u64,
+  #[serde(flatten)]
+  result: &'a RunResult,
+}
+
+#[derive(Default)]
+struct RunLogSystem {
+  reader: Option<ReaderId<GameEvent>>,
+}
+
+impl<'a> System<'a> for RunLogSystem {
+  type SystemData = (Read<'a, EventChannel<GameEvent>>, Read<'a, GameConfig>);
+
+  fn run(&mut self, (events, config): Self::SystemData) {
+    let reader = self.reader.as_mut().expect("RunLogSystem was not set up!");
+    for event in events.read(reader) {
+      if let GameEvent::RunEnded(result) = event {
+        if config.run_log_enabled {
+          if let Err(e) = append_run_log(&config.run_log_path, result) {
+            warn!("Couldn't write run to {}: {}", config.run_log_path, e);
+          }
+        }
+      }
+    }
+  }
+
+  fn setup(&mut self, world: &mut World) {
+    Self::SystemData::setup(world);
+    self.reader = Some(
+      world
+        .fetch_mut::<EventChannel<GameEvent>>()
+        .register_reader(),
+    );
+  }
+}
+
+#[derive(Clone, Debug, Default)]
+struct ReplayFrame {
+  bird: Vector3<f32>,
+  dy: f32,
+  pipes: Vec<(Entity, Vector3<f32>)>,
+  /// Coins, power-ups and gravity wells, put back by a rewind and shown by the death replay.
+  pickups: Vec<(Entity, Vector3<f32>)>,
+  /// Filled in by the `PlayState` after the step, so a rewind spawns the same pipes again.
+  spawn_timer: Option<f32>,
+  next_gap: usize,
+}
+
+/// Ring buffer of the last `DEATH_REPLAY_DURATION` seconds, or `REWIND_DURATION` with
+/// `GameConfig::rewind_enabled`, one frame per physics step.
+#[derive(Debug, Default)]
+struct ReplayBuffer {
+  frames: VecDeque<ReplayFrame>,
+}
+
+struct ReplayRecordSystem;
+
+impl<'a> System<'a> for ReplayRecordSystem {
+  type SystemData = (
+    Entities<'a>,
+    ReadStorage<'a, Bird>,
+    ReadStorage<'a, Pipe>,
+    ReadStorage<'a, PowerUp>,
+    ReadStorage<'a, Coin>,
+    ReadStorage<'a, GravityWell>,
+    ReadStorage<'a, Transform>,
+    Write<'a, ReplayBuffer>,
+    Read<'a, GameConfig>,
+  );
+
+  fn run(
+    &mut self,
+    (entities, birds, pipes, power_ups, coins, wells, transforms, mut replay, config): Self::SystemData,
+  ) {
+    let (bird, dy) = match (&birds, &transforms).join().next() {
+      Some((bird, transform)) => (*transform.translation(), bird.dy),
+      None => return,
+    };
+    let pipes = (&entities, &pipes, &transforms)
+      .join()
+      .map(|(e, _, transform)| (e, *transform.translation()))
+      .collect();
+    let mut pickups: Vec<_> = (&entities, &power_ups, &transforms)
+      .join()
+      .map(|(e, _, transform)| (e, *transform.translation()))
+      .collect();
+    pickups.extend(
+      (&entities, &coins, &transforms)
+        .join()
+        .map(|(e, _, transform)| (e, *transform.translation())),
+    );
+    pickups.extend(
+      (&entities, &wells, &transforms)
+        .join()
+        .map(|(e, _, transform)| (e, *transform.translation())),
+    );
+
+    replay.frames.push_back(ReplayFrame {
+      bird,
+      dy,
+      pipes,
+      pickups,
+      ..ReplayFrame::default()
+    });
+    let kept = if config.rewind_enabled {
+      DEATH_REPLAY_DURATION.max(REWIND_DURATION)
+    } else {
+      DEATH_REPLAY_DURATION
+    };
+    while replay.frames.len() as f32 * PHYSICS_STEP > kept {
+      replay.frames.pop_front();
+    }
+  }
+}
+
+struct CollisionSystem;
+
+impl<'a> System<'a> for CollisionSystem {
+  type SystemData = (
+    Entities<'a>,
+    WriteStorage<'a, Bird>,
+    ReadStorage<'a, Background>,
+    ReadStorage<'a, Pipe>,
+    ReadStorage<'a, PipeEntry>,
+    ReadStorage<'a, PowerUp>,
+    ReadStorage<'a, Coin>,
+    ReadStorage<'a, Transform>,
+    WriteStorage<'a, UiText>,
+    ReadExpect<'a, Score>,
+    ReadExpect<'a, Coins>,
+    Write<'a, RunStats>,
+    WriteExpect<'a, GravityFlip>,
+    WriteExpect<'a, Rewind>,
+    Write<'a, EventChannel<GameEvent>>,
+    Write<'a, ImpactPoint>,
+    Write<'a, Shake>,
+    Read<'a, GameConfig>,
+    Read<'a, GameMode>,
+    Read<'a, StartGrace>,
+    ReadExpect<'a, NoClip>,
+    Read<'a, AssetStorage<Source>>,
+    Option<ReadExpect<'a, Sounds>>,
+    Option<Read<'a, Output>>,
+    WriteStorage<'a, AudioEmitter>,
+  );
+
+  fn run(
+    &mut self,
+    (
+      entities,
+      mut birds,
+      backgrounds,
+      pipes,
+      pipe_entries,
+      power_ups,
+      coins,
+      transforms,
+      mut ui_text,
+      score,
+      coin_counter,
+      mut run_stats,
+      mut gravity_flip,
+      mut rewind,
+      mut event_ch,
+      mut impact,
+      mut shake,
+      config,
+      mode,
+      grace,
+      noclip,
+      storage,
+      sounds,
+      output,
+      mut emitters,
+    ): Self::SystemData,
+  ) {
+    if *mode == GameMode::Zen {
+      return;
+    }
+
+    // The first hit ends the bird's checks, so overlapping both pipes of a pair only hurts once.
+    'birds: for (bird, transform, mut emitter) in
+      (&mut birds, &transforms, (&mut emitters).maybe()).join()
+    {
+      let bird_x = transform.translation().x;
+      let bird_y = transform.translation().y;
+      let impact_shake =
+        shake_magnitude(bird.dy, config.impact_shake_scale, config.impact_shake_max);
+
+      if above_ceiling(bird_y) && *mode != GameMode::Tutorial && !noclip.active {
+        event_ch.single_write(GameEvent::Collision(CollisionCause::Ceiling));
+        impact.point = Some((bird_x, VIRTUAL_HEIGHT / 2.));
+        shake.magnitude = impact_shake;
+        if let Some(sounds) = sounds.as_deref() {
+          play_hurt_sound(
+            sounds,
+            CollisionCause::Ceiling,
+            config.death_sounds,
+            emitter.as_deref_mut(),
+            &storage,
+            output.as_deref(),
+          );
+        }
+        continue;
+      }
+
+      // Power-ups are never lethal, they are grabbed on contact.
+      for (e, power_up, transform) in (&entities, &power_ups, &transforms).join() {
+        let x = transform.translation().x;
+        let y = transform.translation().y;
+        let reach_x = (POWER_UP_SIZE + BIRD_WIDTH) / 2.;
+        let reach_y = (POWER_UP_SIZE + BIRD_HEIGHT) / 2.;
+        if point_in_rect(
+          bird_x,
+          bird_y,
+          x - reach_x,
+          y - reach_y,
+          x + reach_x,
+          y + reach_y,
+        ) {
+          match power_up.kind {
+            PowerUpKind::GravityFlip => gravity_flip.remaining = GRAVITY_FLIP_DURATION,
+            PowerUpKind::Rewind => {
+              rewind.charges += 1;
+              if let Some(text) = ui_text.get_mut(rewind.text) {
+                text.text = rewind.label();
+              }
+            }
+          }
+          entities
+            .delete(e)
+            .expect("Error while removing a grabbed power-up!");
+          if let Some(sounds) = sounds.as_deref() {
+            play_score_sound(sounds, emitter.as_deref_mut(), &storage, output.as_deref());
+          }
+        }
+      }
+
+      for (e, _, transform) in (&entities, &coins, &transforms).join() {
+        let x = transform.translation().x;
+        let y = transform.translation().y;
+        let reach_x = (COIN_SIZE + BIRD_WIDTH) / 2.;
+        let reach_y = (COIN_SIZE + BIRD_HEIGHT) / 2.;
+        if point_in_rect(
+          bird_x,
+          bird_y,
+          x - reach_x,
+          y - reach_y,
+          x + reach_x,
+          y + reach_y,
+        ) {
+          run_stats.coins += 1;
+          if let Some(text) = ui_text.get_mut(coin_counter.text) {
+            text.text = format!("Coins: {}", run_stats.coins);
+          }
+          entities
+            .delete(e)
+            .expect("Error while removing a collected coin!");
+          if let Some(sounds) = sounds.as_deref() {
+            play_coin_sound(sounds, &storage, output.as_deref());
+          }
+        }
+      }
+
+      // Pickups are still grabbed without clipping, only the obstacles are skipped.
+      if noclip.active {
+        continue;
+      }
+
+      for (_, transform, entry) in (&pipes, &transforms, pipe_entries.maybe()).join() {
+        if grace.remaining > 0. {
+          break;
+        }
+        if entry.is_some() && !config.pipe_entry_collision {
+          continue;
+        }
+        let pipe_rect = config.pipe_hitbox(transform.translation().x, transform.translation().y);
+        let (pipe_left, pipe_bottom, pipe_right, pipe_top) = pipe_rect;
+
+        if !bird_hits_rect(
+          &config,
+          bird_x,
+          bird_y,
+          pipe_left,
+          pipe_bottom,
+          pipe_right,
+          pipe_top,
+        ) {
+          continue;
+        }
+        let (left, bottom, right, top) = (
+          pipe_left - BIRD_WIDTH / 2.,
+          pipe_bottom - BIRD_HEIGHT / 2.,
+          pipe_right + BIRD_WIDTH / 2.,
+          pipe_top + BIRD_HEIGHT / 2.,
+        );
+        if *mode != GameMode::Bounce {
+          event_ch.single_write(GameEvent::Collision(CollisionCause::Pipe));
+          impact.point = Some(overlap_center(bird_x, bird_y, pipe_rect));
+          shake.magnitude = impact_shake;
+          if let Some(sounds) = sounds.as_deref() {
+            play_hurt_sound(
+              sounds,
+              CollisionCause::Pipe,
+              config.death_sounds,
+              emitter.as_deref_mut(),
+              &storage,
+              output.as_deref(),
+            );
+          }
+          continue 'birds;
+        }
+
+        if bird.bounce_grace > 0. || bird.bounce.is_some() {
+          continue;
+        }
+        bird.bounce = Some(separation_vector(bird_x, bird_y, left, bottom, right, top));
+        bird.score -= BOUNCE_HIT_COST;
+        if let Some(sounds) = sounds.as_deref() {
+          play_hurt_sound(
+            sounds,
+            CollisionCause::Pipe,
+            config.death_sounds,
+            emitter.as_deref_mut(),
+            &storage,
+            output.as_deref(),
+          );
+        }
+        if let Some(text) = ui_text.get_mut(score.text) {
+          text.text = format_score(bird.score.max(0), config.score_format);
+        }
+        if bird.score <= 0 {
+          event_ch.single_write(GameEvent::Collision(CollisionCause::Pipe));
+          impact.point = Some(overlap_center(bird_x, bird_y, pipe_rect));
+          shake.magnitude = impact_shake;
+          continue 'birds;
+        }
+      }
+
+      for (background, transform) in (&backgrounds, &transforms).join() {
+        match background.b_type {
+          BackgroundType::Background => {}
+          // The tutorial keeps the bird above the ground, only the pipe counts.
+          BackgroundType::Ground if *mode == GameMode::Tutorial => {}
+          BackgroundType::Ground => {
+            let ground_rect =
+              config.ground_hitbox(transform.translation().x, transform.translation().y);
+            if bird_hits_rect(
+              &config,
+              bird_x,
+              bird_y,
+              ground_rect.0,
+              ground_rect.1,
+              ground_rect.2,
+              ground_rect.3,
+            ) {
+              event_ch.single_write(GameEvent::Collision(CollisionCause::Ground));
+              impact.point = Some(overlap_center(bird_x, bird_y, ground_rect));
+              shake.magnitude = impact_shake;
+              if let Some(sounds) = sounds.as_deref() {
+                play_hurt_sound(
+                  sounds,
+                  CollisionCause::Ground,
+                  config.death_sounds,
+                  emitter.as_deref_mut(),
+                  &storage,
+                  output.as_deref(),
+                );
+              }
+              continue 'birds;
+            }
+          }
+        }
+      }
+    }
+  }
+}
+
+/// Tints the bird with a pulsing blue shield while the start grace lasts.
+struct StartGraceSystem;
+
+impl<'a> System<'a> for StartGraceSystem {
+  type SystemData = (
+    ReadStorage<'a, Bird>,
+    WriteStorage<'a, Tint>,
+    Read<'a, StartGrace>,
+    Read<'a, Settings>,
+  );
+
+  fn run(&mut self, (birds, mut tints, grace, settings): Self::SystemData) {
+    let base = settings.bird_tint().0;
+    for (_, tint) in (&birds, &mut tints).join() {
+      *tint = if grace.remaining > 0. {
+        let pulse = 0.75 + 0.25 * (grace.remaining * 10.).sin();
+        Tint(Srgba::new(
+          base.red * pulse,
+          base.green * pulse,
+          base.blue,
+          base.alpha,
+        ))
+      } else {
+        Tint(base)
+      };
+    }
+  }
+}
+
+struct ScoreSystem;
+
+impl<'a> System<'a> for ScoreSystem {
+  type SystemData = (
+    WriteStorage<'a, Bird>,
+    WriteStorage<'a, Pipe>,
+    ReadStorage<'a, Transform>,
+    WriteStorage<'a, UiText>,
+    WriteStorage<'a, UiTransform>,
+    Read<'a, UiScale>,
+    ReadExpect<'a, Score>,
+    WriteExpect<'a, Combo>,
+    WriteExpect<'a, Milestone>,
+    Write<'a, RunStats>,
+    Read<'a, GameMode>,
+    Write<'a, EventChannel<GameEvent>>,
+    WriteStorage<'a, Hidden>,
+    WriteStorage<'a, Tint>,
+    Entities<'a>,
+    Read<'a, LazyUpdate>,
+    ReadExpect<'a, ParticleSprite>,
+    Read<'a, GameConfig>,
+    Read<'a, AssetStorage<Source>>,
+    ReadExpect<'a, Sounds>,
+    Option<Read<'a, Output>>,
+    WriteStorage<'a, AudioEmitter>,
+  );
+
+  fn run(
+    &mut self,
+    (
+      mut birds,
+      mut pipes,
+      transforms,
+      mut ui_text,
+      mut ui_transforms,
+      ui_scale,
+      score,
+      mut combo,
+      mut milestone,
+      mut run_stats,
+      mode,
+      mut event_ch,
+      mut hidden,
+      mut tints,
+      entities,
+      lazy,
+      particle_sprite,
+      config,
+      storage,
+      sounds,
+      output,
+      mut emitters,
+    ): Self::SystemData,
+  ) {
+    if milestone.remaining > 0. {
+      milestone.remaining = (milestone.remaining - PHYSICS_STEP).max(0.);
+      if milestone.remaining <= 0. {
+        hidden
+          .insert(milestone.text, Hidden)
+          .expect("Error while trying to hide the milestone banner!");
+      }
+    }
+
+    if combo.break_flash > 0. {
+      combo.break_flash = (combo.break_flash - PHYSICS_STEP).max(0.);
+      if combo.break_flash <= 0. {
+        if let Some(text) = ui_text.get_mut(combo.text) {
+          text.text.clear();
+        }
+      }
+    }
+
+    if combo.count > 0 && config.combo_timeout.is_some() {
+      combo.remaining -= PHYSICS_STEP;
+      if combo.remaining <= 0. && combo.break_streak() {
+        if config.combo_break_sound {
+          play_combo_lost_sound(&*sounds, &storage, output.as_deref());
+        }
+        if let Some(text) = ui_text.get_mut(combo.text) {
+          text.color = [1., 0.2, 0.2, 1.];
+        }
+      }
+    }
+
+    // Bottom edges of the upper pipes, to measure how close a pass came to either side.
+    let upper_pipes: Vec<(f32, f32)> = (&pipes, &transforms)
+      .join()
+      .map(|(_, transform)| transform.translation())
+      .filter(|translation| translation.y > 0.)
+      .map(|translation| (translation.x, translation.y - PIPE_HEIGHT / 2.))
+      .collect();
+
+    // Positions are compared as if the world always scrolled to the left.
+    let sign = config.scroll_direction.sign();
+    let mut scored_pairs = Vec::new();
+    for (bird, transform, mut emitter) in (&mut birds, &transforms, (&mut emitters).maybe()).join()
+    {
+      let bird_x = sign * transform.translation().x;
+      let bird_y = transform.translation().y;
+
+      for (pipe, transform) in (&mut pipes, &transforms).join() {
+        let pipe_x = sign * transform.translation().x + (PIPE_WIDTH / 2.);
+        let pipe_y = transform.translation().y + (PIPE_HEIGHT / 2.);
+
+        // Only the lower pipe of a pair plays sounds, otherwise every one would be heard twice.
+        if !pipe.whooshed && pipe_x - PIPE_WIDTH < bird_x && pipe_y < 0. {
+          pipe.whooshed = true;
+          if config.pipe_whoosh_enabled {
+            play_whoosh_sound(&*sounds, &storage, output.as_deref());
+          }
+        }
+
+        if !pipe.is_scored && pipe_x < bird_x && pipe_y < 0. {
+          pipe.is_scored = true;
+          scored_pairs.push(transform.translation().x);
+
+          let lower_margin = bird_y - BIRD_HEIGHT / 2. - pipe_y;
+          let upper_margin = upper_pipes
+            .iter()
+            .find(|(x, _)| (x - transform.translation().x).abs() < 1.)
+            .map_or(std::f32::MAX, |(_, bottom)| {
+              bottom - (bird_y + BIRD_HEIGHT / 2.)
+            });
+          let kind = config.classify_pass(lower_margin.min(upper_margin));
+          event_ch.single_write(GameEvent::PipePassed(kind));
+          if kind == PassKind::Tight {
+            combo.count += 1;
+            combo.remaining = config.combo_timeout.unwrap_or(0.);
+          } else if combo.break_streak() && config.combo_break_sound {
+            play_combo_lost_sound(&*sounds, &storage, output.as_deref());
+          }
+          let (new_score, score_text) = add_score(
+            bird.score,
+            combo.multiplier() as i32,
+            config.max_score,
+            config.score_format,
+          );
+          bird.score = new_score;
+          run_stats.pipes_passed += 1;
+          if *mode == GameMode::Sprint && run_stats.pipes_passed >= config.sprint_pipes {
+            run_stats.completed = true;
+          }
+
+          play_score_sound(
+            &*sounds,
+            emitter.as_deref_mut(),
+            &storage,
+            output.as_deref(),
+          );
+
+          if let Some(text) = ui_text.get_mut(score.text) {
+            text.text = score_text;
+          }
+
+          let reached = config
+            .milestone_interval
+            .and_then(|interval| milestone_crossed(milestone.reached, bird.score, interval));
+          if let Some(reached) = reached {
+            milestone.reached = reached;
+            let effects = config.milestone_effects;
+            if effects.sound {
+              play_sound(&sounds.milestone_sfx, 0.3, &storage, output.as_deref());
+            }
+            if effects.banner {
+              if let Some(text) = ui_text.get_mut(milestone.text) {
+                text.text = format!("{}!", reached);
+              }
+              hidden.remove(milestone.text);
+              milestone.remaining = MILESTONE_BANNER_DURATION;
+            }
+            if effects.particles {
+              for i in 0..MILESTONE_BURST_COUNT {
+                let angle = i as f32 / MILESTONE_BURST_COUNT as f32 * std::f32::consts::PI * 2.;
+                let mut burst_transform =
+                  Transform::from(Vector3::new(sign * bird_x, bird_y, Z_PARTICLE));
+                burst_transform.set_scale(Vector3::new(1.5, 1.5, 1.));
+                lazy
+                  .create_entity(&entities)
+                  .with(Particle {
+                    dx: angle.cos() * MILESTONE_BURST_SPEED,
+                    dy: angle.sin() * MILESTONE_BURST_SPEED,
+                    age: 0.,
+                    lifetime: MILESTONE_BURST_LIFETIME,
+                  })
+                  .with(particle_sprite.sprite.clone())
+                  .with(burst_transform)
+                  .with(Tint(Srgba::new(1., 0.85, 0.3, 1.)))
+                  .with(Transparent)
+                  .build();
+              }
+            }
+          }
+          if let Some(text) = ui_text.get_mut(combo.text) {
+            if combo.count >= 2 {
+              text.text = format!("Combo x{}", combo.multiplier());
+              text.color = [1., 0.85, 0.3, 1.];
+            } else if combo.break_flash > 0. {
+              text.color = [1., 0.2, 0.2, 1.];
+            }
+          }
+        }
+      }
+    }
+
+    if let Some((r, g, b, a)) = config.scored_pipe_tint.filter(|_| !scored_pairs.is_empty()) {
+      // Only the lower pipe is scored, the upper one of the pair shares its x.
+      for (e, _, transform) in (&entities, &pipes, &transforms).join() {
+        let x = transform.translation().x;
+        if scored_pairs
+          .iter()
+          .any(|scored_x| (scored_x - x).abs() < 1.)
+        {
+          tints
+            .insert(e, Tint(Srgba::new(r, g, b, a)))
+            .expect("Error while tinting a scored pipe!");
+        }
+      }
+    }
+
+    // The meter has no text, so the `UiScaleSystem` leaves it alone and it's scaled here.
+    let fill = match config.combo_timeout {
+      Some(timeout) if combo.count > 0 => (combo.remaining / timeout).max(0.),
+      _ => 0.,
+    };
+    if let Some(transform) = ui_transforms.get_mut(combo.meter) {
+      transform.width = COMBO_METER_WIDTH * fill * ui_scale.factor;
+      transform.height = COMBO_METER_HEIGHT * ui_scale.factor;
+      transform.local_x = config.hud.combo.x * ui_scale.factor;
+      transform.local_y = (COMBO_METER_Y + config.hud.combo.y) * ui_scale.factor;
+    }
+  }
+}
+
+#[derive(Default)]
+struct TitleScreenState {
+  logo_sprite: Option<SpriteRender>,
+  bird_sprite: Option<SpriteRender>,
+  /// Skips straight into a staged run, see `PlayState::screenshot`.
+  screenshot: bool,
+}
+
+impl TitleScreenState {
+  /// The logo and the bobbing bird only exist while the title screen is on top.
+  fn spawn_decorations(&self, world: &mut World) {
+    if let Some(sprite) = self.logo_sprite.clone() {
+      world
+        .create_entity()
+        .with(TitleBob {
+          base_y: TITLE_LOGO_Y,
+          amplitude: 3.,
+          phase: 0.,
+        })
+        .with(sprite)
+        .with(Transform::from(Vector3::new(0., TITLE_LOGO_Y, Z_LOGO)))
+        .build();
+    }
+    if let Some(sprite) = self.bird_sprite.clone() {
+      let facing = world.read_resource::<GameConfig>().scroll_direction.sign();
+      let mut transform = Transform::from(Vector3::new(0., TITLE_BIRD_Y, Z_BIRD));
+      transform.set_scale(Vector3::new(facing, 1., 1.));
+      let tint = world.read_resource::<Settings>().bird_tint();
+      world
+        .create_entity()
+        .with(TitleBob {
+          base_y: TITLE_BIRD_Y,
+          amplitude: world.read_resource::<GameConfig>().title_bob_amplitude,
+          phase: std::f32::consts::FRAC_PI_2,
+        })
+        .with(sprite)
+        .with(transform)
+        .with(tint)
+        .build();
+    }
+  }
+
+  fn delete_decorations(&self, world: &mut World) {
+    let entities = world.entities();
+    let bobs = world.read_storage::<TitleBob>();
+    for (e, _) in (&entities, &bobs).join() {
+      entities
+        .delete(e)
+        .expect("Couldn't delete title decoration!");
+    }
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for TitleScreenState {
+  fn on_start(&mut self, _data: StateData<'_, GameData<'_, '_>>) {
+    let world = _data.world;
+
+    init_audio(world);
+
+    let background_sprite =
+      load_sprite("texture/background.png", "texture/background.ron", 0, world);
+    let ground_sprite = load_sprite("texture/ground.png", "texture/ground.ron", 0, world);
+
+    let ui_root = world.exec(|mut creator: UiCreator<'_>| creator.create(UI_LAYOUT, ()));
+    world.insert(UiRoot { entity: ui_root });
+
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(MENU_FONT, TtfFormat, (), &world.read_resource());
+    let debug_text = world
+      .create_entity()
+      .with(UiTransform::new(
+        "debug_overlay".to_string(),
+        Anchor::TopLeft,
+        Anchor::TopLeft,
+        10.,
+        -10.,
+        2.,
+        1000.,
+        30.,
+      ))
+      .with(UiText::new(
+        font.clone(),
+        String::new(),
+        [1., 1., 0., 1.],
+        24.,
+        LineMode::Single,
+        Anchor::TopLeft,
+      ))
+      .with(Hidden)
+      .build();
+    world.insert(DebugOverlay { text: debug_text });
+    let profiler_text = world
+      .create_entity()
+      .with(UiTransform::new(
+        "profiler_overlay".to_string(),
+        Anchor::TopLeft,
+        Anchor::TopLeft,
+        10.,
+        -45.,
+        2.,
+        600.,
+        200.,
+      ))
+      .with(UiText::new(
+        font,
+        String::new(),
+        [0.5, 1., 0.5, 1.],
+        24.,
+        LineMode::Wrap,
+        Anchor::TopLeft,
+      ))
+      .with(Hidden)
+      .build();
+    world.insert(Profiler {
+      text: profiler_text,
+      enabled: Arc::new(AtomicBool::new(false)),
+      timings: Vec::new(),
+    });
+
+    let flash_overlay = world
+      .create_entity()
+      .with(
+        UiTransform::new(
+          "flash".to_string(),
+          Anchor::Middle,
+          Anchor::Middle,
+          0.,
+          0.,
+          10.,
+          0.,
+          0.,
+        )
+        .into_transparent()
+        .with_stretch(Stretch::XY {
+          x_margin: 0.,
+          y_margin: 0.,
+          keep_aspect_ratio: false,
+        }),
+      )
+      .with(UiImage::SolidColor([0., 0., 0., 0.]))
+      .build();
+    world.insert(Flash {
+      overlay: flash_overlay,
+      alpha: 0.,
+    });
+
+    world
+      .create_entity()
+      .with(Background {
+        b_type: BackgroundType::Background,
+        scroll_pos: 0.,
+        validated: false,
+      })
+      .with(background_sprite)
+      .with(Transform::from(Vector3::new(
+        BACKGROUND_LOOPING_OFFSET,
+        0.,
+        Z_BACKGROUND,
+      )))
+      .build();
+
+    world
+      .create_entity()
+      .with(Background {
+        b_type: BackgroundType::Ground,
+        scroll_pos: 0.,
+        validated: false,
+      })
+      .with(ground_sprite)
+      .with(Transform::from(Vector3::new(
+        BACKGROUND_LOOPING_OFFSET,
+        (VIRTUAL_HEIGHT - GROUND_HEIGHT) / -2.,
+        Z_GROUND,
+      )))
+      .build();
+
+    self.logo_sprite.replace(load_sprite(
+      "texture/logo.png",
+      "texture/logo.ron",
+      0,
+      world,
+    ));
+    self.bird_sprite.replace(load_sprite(
+      "texture/bird.png",
+      "texture/bird.ron",
+      0,
+      world,
+    ));
+    self.spawn_decorations(world);
+  }
+
+  fn on_pause(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+    self.delete_decorations(world);
+
+    set_title_hidden(world, true);
+  }
+
+  fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+    self.spawn_decorations(world);
+
+    set_title_hidden(world, false);
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'_, '_>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+        return Trans::Quit;
+      }
+      if is_key_down(&event, VirtualKeyCode::S) {
+        return Trans::Push(Box::new(SettingsState::default()));
+      }
+      if is_key_down(&event, VirtualKeyCode::Z) {
+        return Trans::Push(Box::new(PlayState {
+          mode: GameMode::Zen,
+          ..Default::default()
+        }));
+      }
+      if is_key_down(&event, VirtualKeyCode::B) {
+        return Trans::Push(Box::new(PlayState {
+          mode: GameMode::Bounce,
+          ..Default::default()
+        }));
+      }
+      if is_key_down(&event, VirtualKeyCode::T) {
+        return Trans::Push(Box::new(PlayState {
+          mode: GameMode::Tube,
+          ..Default::default()
+        }));
+      }
+      if is_key_down(&event, VirtualKeyCode::G) {
+        return Trans::Push(Box::new(PlayState {
+          mode: GameMode::Sprint,
+          ..Default::default()
+        }));
+      }
+      if is_key_down(&event, VirtualKeyCode::O) {
+        return Trans::Push(Box::new(ProfileState::default()));
+      }
+      if is_key_down(&event, VirtualKeyCode::R) {
+        return Trans::Push(Box::new(LeaderboardState::default()));
+      }
+      if is_key_down(&event, VirtualKeyCode::V) {
+        match with_clipboard(|clipboard| clipboard.get_contents()) {
+          Ok(code) => match parse_share_code(&code) {
+            Some((seed, score)) => {
+              info!(
+                "Playing the shared run with seed {:x} and {} points",
+                seed, score
+              );
+              return Trans::Push(Box::new(PlayState {
+                shared_seed: Some(seed),
+                ..Default::default()
+              }));
+            }
+            None => warn!("{:?} in the clipboard isn't a share code.", code),
+          },
+          Err(e) => warn!("Couldn't read the clipboard: {}", e),
+        }
+      }
+    }
+    let settings = data.world.read_resource::<Settings>();
+    if is_primary_action(&event, settings.one_button_mode) {
+      let mode = if settings.tutorial_completed {
+        GameMode::Classic
+      } else {
+        GameMode::Tutorial
+      };
+      return Trans::Push(Box::new(PlayState {
+        mode,
+        ..Default::default()
+      }));
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent<StringBindings>> {
+    data.data.update(&data.world);
+    if self.screenshot {
+      self.screenshot = false;
+      return Trans::Push(Box::new(PlayState {
+        screenshot: true,
+        ..Default::default()
+      }));
+    }
+    Trans::None
+  }
+}
+
+#[derive(Default)]
+struct PlayState {
+  pipe_spawn_timer: Option<f32>,
+  pipe_sprite: Option<SpriteRender>,
+  coin_sprite: Option<SpriteRender>,
+  /// Drawn for the gravity wells.
+  ring_sprite: Option<SpriteRender>,
+  bird_sprite: Option<SpriteRender>,
+  rand: Option<StdRng>,
+  seed: u64,
+  dispatcher: Option<Dispatcher<'static, 'static>>,
+  /// Moves the bird and checks its collisions again for the substeps after the first.
+  substep_dispatcher: Option<Dispatcher<'static, 'static>>,
+  /// Distinguishes the game over screen from a plain pause, only the former ends the run.
+  game_over: bool,
+  /// The game over screen follows as soon as the death replay is done.
+  game_over_pending: bool,
+  mode: GameMode,
+  /// Pipe pairs spawned this run, picks the frame for `PipeFrames::Alternate`.
+  pipes_spawned: usize,
+  /// Started with `--screenshot`, the first run is composed from a fixed seed and paused.
+  screenshot: bool,
+  /// Replays a run pasted as a `share_code`, every retry flies the same pipes again.
+  shared_seed: Option<u64>,
+  /// Progress of a `GameMode::Tutorial` run.
+  tutorial: Option<Tutorial>,
+  /// Lower pipe, gap center and gap size of the pair spawned last, for
+  /// `GameConfig::reachability_check`.
+  last_pair: Option<(Entity, f32, f32)>,
+  /// Set between the collision and the bird hitting the ground with `GameConfig::death_fall`,
+  /// nothing but the bird moves and input is ignored.
+  dying: bool,
+  /// Shows the strength while `Settings::assist_strength` flaps for the player.
+  assist_text: Option<Entity>,
+  /// Asks for a second `Escape` with `GameConfig::confirm_quit`, and the seconds it's still up.
+  quit_prompt: Option<(Entity, f32)>,
+  /// Cover everything above and below the playfield in a `GameMode::Tube` run.
+  tube_masks: Vec<Entity>,
+  gap_source: GapSource,
+  /// Index of the next gap of a `GapSource::Scripted`, counting past the end when it loops.
+  next_gap: usize,
+  /// Set by a rewind, the rest of the collisions from before it are ignored.
+  rewound: bool,
+}
+
+/// Steps of the tutorial, each waits for the player to do what its hint asks for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum TutorialStep {
+  Flap,
+  PassPipe,
+  /// Shown for `TUTORIAL_GOAL_DURATION`, then a regular run starts.
+  Goal,
+}
+
+impl TutorialStep {
+  fn hint(self) -> &'static str {
+    match self {
+      TutorialStep::Flap => "Press Space to flap",
+      TutorialStep::PassPipe => "Fly through the gap",
+      TutorialStep::Goal => "Every pipe is a point, don't touch them or the ground!",
+    }
+  }
+}
+
+struct Tutorial {
+  step: TutorialStep,
+  text: Entity,
+  flaps: u32,
+  /// Seconds the current step has been shown.
+  elapsed: f32,
+}
+
+impl PlayState {
+  /// Every run gets its own seed so a pipe sequence can be traced back through the run log.
+  fn reseed(&mut self) {
+    self.seed = if self.screenshot {
+      SCREENSHOT_SEED
+    } else if let Some(seed) = self.shared_seed {
+      seed
+    } else {
+      thread_rng().gen()
+    };
+    self.rand.replace(StdRng::seed_from_u64(self.seed));
+  }
+
+  /// Places a pair the bird just passed and the next one, with the bird lined up for its gap.
+  fn compose_screenshot(&mut self, world: &mut World) {
+    let (rand, sprite) = match (self.rand.as_mut(), self.pipe_sprite.clone()) {
+      (Some(rand), Some(sprite)) => (rand, sprite),
+      _ => return,
+    };
+    let sign = world.read_resource::<GameConfig>().scroll_direction.sign();
+    let mut next_gap_y = 0.;
+    for &x in &[-SCREENSHOT_PIPE_X, SCREENSHOT_PIPE_X] {
+      next_gap_y = rand.gen_range(-30., 30.);
+      spawn_pipe_pair(world, sprite.clone(), sign * x, next_gap_y, PIPE_GAP);
+    }
+    let mut birds = world.write_storage::<Bird>();
+    let mut transforms = world.write_storage::<Transform>();
+    for (bird, transform) in (&mut birds, &mut transforms).join() {
+      bird.dy = 0.;
+      transform.set_translation_y(next_gap_y);
+    }
+  }
+
+  fn spawn_tutorial_pipe(&self, world: &mut World) {
+    if let Some(sprite) = self.pipe_sprite.clone() {
+      let sign = world.read_resource::<GameConfig>().scroll_direction.sign();
+      let x = sign * (VIRTUAL_WIDTH / 2. + PIPE_WIDTH);
+      spawn_pipe_pair(world, sprite, x, 0., TUTORIAL_GAP);
+    }
+  }
+
+  fn set_tutorial_step(&mut self, world: &World, step: TutorialStep, hint: &str) {
+    if let Some(tutorial) = self.tutorial.as_mut() {
+      tutorial.step = step;
+      tutorial.elapsed = 0.;
+      if let Some(text) = world.write_storage::<UiText>().get_mut(tutorial.text) {
+        text.text = hint.to_string();
+      }
+    }
+  }
+
+  /// Moves on once the hint is followed, returns `true` when the tutorial is done.
+  fn advance_tutorial(&mut self, world: &mut World) -> bool {
+    let (step, flaps, elapsed) = match self.tutorial.as_mut() {
+      Some(tutorial) => {
+        tutorial.elapsed += world.fetch::<Time>().delta_seconds();
+        (tutorial.step, tutorial.flaps, tutorial.elapsed)
+      }
+      None => return false,
+    };
+    match step {
+      TutorialStep::Flap if flaps >= TUTORIAL_FLAPS => {
+        self.set_tutorial_step(world, TutorialStep::PassPipe, TutorialStep::PassPipe.hint());
+        self.spawn_tutorial_pipe(world);
+      }
+      TutorialStep::PassPipe if self.run_result(world).score > 0 => {
+        self.set_tutorial_step(world, TutorialStep::Goal, TutorialStep::Goal.hint());
+      }
+      TutorialStep::Goal => return elapsed >= TUTORIAL_GOAL_DURATION,
+      _ => {}
+    }
+    false
+  }
+
+  /// Hitting the pipe puts a fresh bird and pipe in, the flaps already made still count.
+  fn retry_tutorial_step(&mut self, world: &mut World) {
+    let step = match self.tutorial.as_ref() {
+      Some(tutorial) => tutorial.step,
+      None => return,
+    };
+    delete_run_entities(world);
+    set_score_font(world, &self.start_score(world));
+    if let Some(sprite) = self.bird_sprite.clone() {
+      spawn_bird(world, sprite);
+    }
+    if step != TutorialStep::Flap {
+      self.set_tutorial_step(world, TutorialStep::PassPipe, "Ouch! Try the gap again");
+      self.spawn_tutorial_pipe(world);
+    }
+  }
+
+  /// Starts over with a fresh bird and seed, whatever is left of the last run is removed.
+  fn reset_run(&mut self, world: &mut World) {
+    clear_run(world);
+    world.write_resource::<ImpactPoint>().point = None;
+    self.dying = false;
+    if let Some(tutorial) = self.tutorial.as_mut() {
+      tutorial.flaps = 0;
+    }
+    self.set_tutorial_step(world, TutorialStep::Flap, TutorialStep::Flap.hint());
+    self.reseed();
+    self.pipes_spawned = 0;
+    self.last_pair = None;
+    self.next_gap = 0;
+    self
+      .pipe_spawn_timer
+      .replace(world.read_resource::<GameConfig>().first_pipe_delay);
+    clear_flash(world);
+    set_score_font(world, &self.start_score(world));
+    if let Some(text) = world
+      .write_storage::<UiText>()
+      .get_mut(world.read_resource::<Coins>().text)
+    {
+      text.text = "Coins: 0".to_string();
+    }
+    world.write_resource::<ReplayBuffer>().frames.clear();
+    reset_start_grace(world);
+
+    if let Some(sprite) = self.bird_sprite.clone() {
+      spawn_bird(world, sprite);
+    }
+  }
+
+  /// Drops the bird nose first onto the ground, returns `true` once it's there.
+  fn animate_death(&self, world: &World, steps: u32) -> bool {
+    let facing = world.read_resource::<GameConfig>().scroll_direction.sign();
+    let floor = VIRTUAL_HEIGHT / -2. + GROUND_HEIGHT + BIRD_HEIGHT / 2.;
+    let mut birds = world.write_storage::<Bird>();
+    let mut transforms = world.write_storage::<Transform>();
+    let mut landed = true;
+    for (bird, transform) in (&mut birds, &mut transforms).join() {
+      for _ in 0..steps {
+        bird.dy += BIRD_GRAVITY * PHYSICS_STEP;
+        transform.prepend_translation_y(bird.dy);
+      }
+      let y = transform.translation().y;
+      if y > floor {
+        landed = false;
+      } else {
+        transform.set_translation_y(floor);
+      }
+      // The faster it falls the further the nose tips down, straight down at `BIRD_JUMP`.
+      let tilt = (bird.dy / BIRD_JUMP).max(-1.).min(0.);
+      transform.set_rotation_2d(facing * tilt * std::f32::consts::FRAC_PI_2);
+    }
+    landed
+  }
+
+  /// Reports the finished run and moves on to the death replay or the game over, a completed
+  /// sprint skips the replay.
+  fn end_run<'a, 'b>(&mut self, world: &World) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    let result = self.run_result(world);
+    let completed = result.completed;
+    world
+      .write_resource::<EventChannel<GameEvent>>()
+      .single_write(GameEvent::RunEnded(result));
+    if !completed && world.read_resource::<GameConfig>().death_replay_enabled {
+      let mut frames = std::mem::take(&mut world.write_resource::<ReplayBuffer>().frames);
+      // Rewinds keep a longer buffer than the replay shows.
+      while frames.len() as f32 * PHYSICS_STEP > DEATH_REPLAY_DURATION {
+        frames.pop_front();
+      }
+      if !frames.is_empty() {
+        self.game_over_pending = true;
+        return Trans::Push(Box::new(DeathReplayState::new(frames)));
+      }
+    }
+    self.game_over = true;
+    Trans::Push(Box::new(GameOverState::default()))
+  }
+
+  fn start_score(&self, world: &World) -> String {
+    format_score(
+      self.mode.start_points(),
+      world.read_resource::<GameConfig>().score_format,
+    )
+  }
+
+  /// Enters the run into the leaderboard and the best coin count. Returns the most coins of a run
+  /// and whether this one is the new best of its leaderboard.
+  fn record_run(&self, world: &World) -> (u32, bool) {
+    let (coins, noclip_used) = {
+      let run_stats = world.read_resource::<RunStats>();
+      (run_stats.coins, run_stats.noclip_used)
+    };
+    let entry = ScoreEntry {
+      score: self.run_result(world).score,
+      date: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0),
+    };
+    let scored = entry.score > 0;
+    let mut records = world.write_resource::<Records>();
+    let rank = if noclip_used {
+      None
+    } else {
+      records.leaderboards.insert(self.mode, entry)
+    };
+    if !noclip_used && (coins > records.best_coins || rank.is_some()) {
+      records.best_coins = records.best_coins.max(coins);
+      save_records(world.read_resource::<Settings>().profile, &records);
+    }
+    (records.best_coins, scored && rank == Some(0))
+  }
+
+  /// Leaving in the middle of a run still counts it, just like a death would.
+  fn count_run(&self, world: &World) {
+    let result = self.run_result(world);
+    let config = world.read_resource::<GameConfig>();
+    // The `RunLogSystem` doesn't get to run again once the game stops, a run waiting for its
+    // game over was logged when it ended.
+    if config.run_log_enabled && !self.game_over_pending {
+      if let Err(e) = append_run_log(&config.run_log_path, &result) {
+        warn!("Couldn't write run to {}: {}", config.run_log_path, e);
+      }
+    }
+    self.record_run(world);
+  }
+
+  fn quit<'a, 'b>(&mut self, world: &World) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    self.count_run(world);
+    Trans::Quit
+  }
+
+  /// Quits right away without `GameConfig::confirm_quit`, otherwise only once it's asked twice.
+  fn request_quit<'a, 'b>(&mut self, world: &mut World) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if self.quit_prompt.is_some() || !world.read_resource::<GameConfig>().confirm_quit {
+      return self.quit(world);
+    }
+    let prompt = Self::spawn_quit_prompt(world, -120.);
+    self.quit_prompt = Some((prompt, QUIT_CONFIRM_DURATION));
+    Trans::None
+  }
+
+  /// Puts the prompt `y` pixels from the middle of the screen.
+  fn spawn_quit_prompt(world: &mut World, y: f32) -> Entity {
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(MENU_FONT, TtfFormat, (), &world.read_resource());
+    world
+      .create_entity()
+      .with(UiTransform::new(
+        "quit_prompt".to_string(),
+        Anchor::Middle,
+        Anchor::Middle,
+        0.,
+        y,
+        1.,
+        800.,
+        50.,
+      ))
+      .with(UiText::new(
+        font,
+        "Press Escape again to quit".to_string(),
+        [1., 1., 1., 1.],
+        36.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .build()
+  }
+
+  fn run_result(&self, world: &World) -> RunResult {
+    let score = (&world.read_storage::<Bird>())
+      .join()
+      .map(|bird| bird.score)
+      .next()
+      .unwrap_or(0);
+    let run_stats = world.read_resource::<RunStats>();
+    RunResult {
+      score,
+      duration: run_stats.survival_time,
+      seed: self.seed,
+      coins: run_stats.coins,
+      completed: run_stats.completed,
+    }
+  }
+
+  /// Spends a `Rewind` charge to put the run back to the oldest buffered frame, pipes and
+  /// pickups spawned since are removed again. Returns `false` without a charge.
+  fn rewind(&mut self, world: &mut World) -> bool {
+    {
+      let mut rewind = world.write_resource::<Rewind>();
+      if rewind.charges == 0 {
+        return false;
+      }
+      let frame = match world.write_resource::<ReplayBuffer>().frames.pop_front() {
+        Some(frame) => frame,
+        None => return false,
+      };
+      rewind.charges -= 1;
+      let mut ui_text = world.write_storage::<UiText>();
+      if let Some(text) = ui_text.get_mut(rewind.text) {
+        text.text = rewind.label();
+      }
+
+      let entities = world.entities();
+      let mut transforms = world.write_storage::<Transform>();
+      for (bird, transform) in (&mut world.write_storage::<Bird>(), &mut transforms).join() {
+        transform.set_translation(frame.bird);
+        bird.dy = frame.dy;
+        bird.bounce = None;
+        bird.jump_ease = 0;
+      }
+      let pipes = world.read_storage::<Pipe>();
+      let power_ups = world.read_storage::<PowerUp>();
+      let coins = world.read_storage::<Coin>();
+      let wells = world.read_storage::<GravityWell>();
+      let saved: HashMap<Entity, Vector3<f32>> = frame
+        .pipes
+        .iter()
+        .chain(frame.pickups.iter())
+        .cloned()
+        .collect();
+      let run_entities = (&entities).join().filter(|e| {
+        pipes.contains(*e) || power_ups.contains(*e) || coins.contains(*e) || wells.contains(*e)
+      });
+      for e in run_entities {
+        match saved.get(&e) {
+          Some(translation) => {
+            if let Some(transform) = transforms.get_mut(e) {
+              transform.set_translation(*translation);
+            }
+          }
+          None => entities
+            .delete(e)
+            .expect("Error while removing an entity spawned after the rewind point!"),
+        }
+      }
+      self.pipe_spawn_timer = frame.spawn_timer;
+      self.next_gap = frame.next_gap;
+      world.write_resource::<ReplayBuffer>().frames.clear();
+    }
+    reset_start_grace(world);
+    // The rewound run didn't end there after all.
+    world.write_resource::<ImpactPoint>().point = None;
+    self.rewound = true;
+    true
+  }
+
+  /// Only a scripted level which ends the run ever runs out of gaps.
+  fn level_exhausted(&self) -> bool {
+    match &self.gap_source {
+      GapSource::Scripted(level) => {
+        level.when_done == LevelEnd::EndRun && self.next_gap >= level.gaps.len()
+      }
+      GapSource::Random => false,
+    }
+  }
+
+  fn spawn_pipes(&mut self, world: &mut World) {
+    if self.mode == GameMode::Zen || self.mode == GameMode::Tutorial || self.level_exhausted() {
+      return;
+    }
+    let rand = match self.rand.as_mut() {
+      Some(rand) => rand,
+      None => return,
+    };
+    let hurry_up = world
+      .read_resource::<RunStats>()
+      .hurry_up(&world.read_resource::<GameConfig>());
+    if let Some(mut timer) = self.pipe_spawn_timer.take() {
+      timer -= PHYSICS_STEP * (1. + hurry_up);
+      if timer <= 0.0 {
+        let (
+          direction,
+          pipe_frames,
+          wave_count,
+          wave_spacing,
+          power_ups_enabled,
+          rewind_enabled,
+          coin_chance,
+          reachability_check,
+          entry_duration,
+          (gap_min, gap_max),
+          gravity_wells,
+        ) = {
+          let config = world.read_resource::<GameConfig>();
+          (
+            config.scroll_direction,
+            config.pipe_frames,
+            config.pipe_wave_count.max(1),
+            config.pipe_wave_spacing,
+            config.power_ups_enabled,
+            config.rewind_enabled,
+            config.coin_chance,
+            config.reachability_check,
+            config.pipe_entry_duration,
+            (config.pipe_gap_min, config.pipe_gap_max),
+            config.gravity_wells,
+          )
+        };
+        if let Some(mut sprite) = self.pipe_sprite.clone() {
+          let frames = world
+            .read_resource::<AssetStorage<SpriteSheet>>()
+            .get(&sprite.sprite_sheet)
+            .map_or(1, |sheet| sheet.sprites.len().max(1));
+          let shrink = 1. - hurry_up * HURRY_UP_GAP_SHRINK;
+          // Every pair of a wave trails the previous one and gets its own gap height.
+          for i in 0..wave_count {
+            let spawn_x =
+              direction.sign() * (VIRTUAL_WIDTH / 2. + PIPE_WIDTH + i as f32 * wave_spacing);
+            // Both pipes of a pair share the frame so a capped pipe never faces a plain one.
+            sprite.sprite_number = match pipe_frames {
+              PipeFrames::First => 0,
+              PipeFrames::Random => rand.gen_range(0, frames),
+              PipeFrames::Alternate => self.pipes_spawned % frames,
+            };
+            self.pipes_spawned += 1;
+            let gap = pick_gap(rand, gap_min, gap_max, shrink);
+            let rand_bot = rand.gen_range(-40., -20.);
+            let rand_top = rand.gen_range(20., 40.);
+            let mut random_y = rand.gen_range(rand_bot, rand_top);
+            if let GapSource::Scripted(level) = &self.gap_source {
+              if level.when_done == LevelEnd::EndRun && self.next_gap >= level.gaps.len() {
+                break;
+              }
+              random_y = level.gaps[self.next_gap % level.gaps.len()];
+              self.next_gap += 1;
+            }
+            // A scripted level is played as written, `load_gap_source` reported what can't be reached.
+            let scripted = matches!(self.gap_source, GapSource::Scripted(_));
+            if let (true, false, Some((pipe, from_y, from_gap))) =
+              (reachability_check, scripted, self.last_pair)
+            {
+              // The last pair is gone once it scrolled out, then anything is reachable.
+              if let Some(transform) = world.read_storage::<Transform>().get(pipe) {
+                let dx = spawn_x - transform.translation().x;
+                let speed = -PIPE_SCROLL * (1. + hurry_up);
+                random_y = clamp_to_reachable(random_y, from_y, from_gap, dx, speed, gap);
+              }
+            }
+            let (pipe, upper) = spawn_pipe_pair(world, sprite.clone(), spawn_x, random_y, gap);
+            self.last_pair = Some((pipe, random_y, gap));
+            if entry_duration > 0. {
+              let transforms = world.read_storage::<Transform>();
+              let mut entries = world.write_storage::<PipeEntry>();
+              for (e, offset) in [(pipe, -PIPE_ENTRY_OFFSET), (upper, PIPE_ENTRY_OFFSET)].iter() {
+                if let Some(transform) = transforms.get(*e) {
+                  entries
+                    .insert(
+                      *e,
+                      PipeEntry {
+                        target_y: transform.translation().y,
+                        offset: *offset,
+                        elapsed: 0.,
+                      },
+                    )
+                    .expect("Error while adding a pipe entry!");
+                }
+              }
+            }
+            if (power_ups_enabled || rewind_enabled) && rand.gen_bool(POWER_UP_CHANCE) {
+              let kind = if !power_ups_enabled || (rewind_enabled && rand.gen_bool(0.5)) {
+                PowerUpKind::Rewind
+              } else {
+                PowerUpKind::GravityFlip
+              };
+              let tint = match kind {
+                PowerUpKind::GravityFlip => Srgba::new(0.7, 0.3, 1., 1.),
+                PowerUpKind::Rewind => Srgba::new(0.3, 1., 0.6, 1.),
+              };
+              let sprite = world.read_resource::<ParticleSprite>().sprite.clone();
+              let mut transform = Transform::from(Vector3::new(spawn_x, random_y, Z_PICKUP));
+              transform.set_scale(Vector3::new(POWER_UP_SIZE / 4., POWER_UP_SIZE / 4., 1.));
+              world
+                .create_entity()
+                .with(PowerUp { kind })
+                .with(sprite)
+                .with(transform)
+                .with(Tint(tint))
+                .build();
+            } else if let Some(sprite) = self.coin_sprite.clone() {
+              if rand.gen_bool(coin_chance) {
+                // Off the middle of the gap, so the bird has to go a bit out of its way.
+                let y = random_y + rand.gen_range(-gap / 4., gap / 4.);
+                let mut transform = Transform::from(Vector3::new(spawn_x, y, Z_PICKUP));
+                transform.set_scale(Vector3::new(COIN_SIZE / 16., COIN_SIZE / 16., 1.));
+                world
+                  .create_entity()
+                  .with(Coin)
+                  .with(sprite)
+                  .with(transform)
+                  .build();
+              }
+            }
+            if let (Some(wells), Some(sprite)) = (gravity_wells, self.ring_sprite.clone()) {
+              // Without wells the random sequence of the seed stays as it was.
+              if rand.gen_bool(wells.chance) {
+                let x = spawn_x + direction.sign() * -PIPE_SCROLL * GRAVITY_WELL_LEAD;
+                let y = rand.gen_range(-VIRTUAL_HEIGHT / 4., VIRTUAL_HEIGHT / 4.);
+                world
+                  .create_entity()
+                  .with(GravityWell {
+                    radius: wells.radius,
+                    strength: wells.strength,
+                    phase: 0.,
+                  })
+                  .with(sprite)
+                  .with(Transform::from(Vector3::new(x, y, Z_PICKUP)))
+                  .with(Tint(Srgba::new(0.6, 0.4, 1., 0.6)))
+                  .with(Transparent)
+                  .build();
+              }
+            }
+          }
+        }
+        // The next wave waits until the tail of this one has scrolled in.
+        let wave_length = (wave_count - 1) as f32 * wave_spacing / -PIPE_SCROLL;
+        self
+          .pipe_spawn_timer
+          .replace(rand.gen_range(2., 4.) + wave_length);
+      } else {
+        self.pipe_spawn_timer.replace(timer);
+      }
+    }
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for PlayState {
+  fn on_start(&mut self, _data: StateData<'_, GameData<'_, '_>>) {
+    let world = _data.world;
+
+    // The profiler has to be released again before the systems are set up.
+    let mut dispatcher = {
+      let mut profiler = world.write_resource::<Profiler>();
+      profiler.timings.clear();
+      let mut dispatcher_builder = DispatcherBuilder::new();
+      dispatcher_builder.add(profiler.profile("Bird", BirdSystem), "bird_system", &[]);
+      dispatcher_builder.add(profiler.profile("Pipe", PipeSystem), "pipe_system", &[]);
+      dispatcher_builder.add(PipeEntrySystem, "pipe_entry_system", &["pipe_system"]);
+      if self.mode != GameMode::Zen && self.mode != GameMode::Tutorial {
+        dispatcher_builder.add(HurryUpSystem, "hurry_up_system", &[]);
+      }
+      dispatcher_builder.add(ParticleSystem, "particle_system", &[]);
+      dispatcher_builder.add(PowerUpSystem, "power_up_system", &[]);
+      dispatcher_builder.add(CoinSystem, "coin_system", &[]);
+      dispatcher_builder.add(GravityWellSystem, "gravity_well_system", &[]);
+      dispatcher_builder.add(RhythmSystem, "rhythm_system", &["bird_system"]);
+      dispatcher_builder.add(StartGraceSystem, "start_grace_system", &[]);
+      dispatcher_builder.add(
+        ReplayRecordSystem,
+        "replay_record_system",
+        &["bird_system", "pipe_system"],
+      );
+      dispatcher_builder.add(
+        profiler.profile("Collision", CollisionSystem),
+        "collision_system",
+        &["bird_system", "pipe_system", "pipe_entry_system"],
+      );
+      dispatcher_builder.add(
+        profiler.profile("Score", ScoreSystem),
+        "score_system",
+        &["bird_system", "pipe_system"],
+      );
+      dispatcher_builder.build()
+    };
+    dispatcher.setup(world);
+    self.dispatcher = Some(dispatcher);
+    let mut substep_dispatcher = DispatcherBuilder::new()
+      .with(BirdSystem, "bird_system", &[])
+      .with(CollisionSystem, "collision_system", &["bird_system"])
+      .build();
+    substep_dispatcher.setup(world);
+    self.substep_dispatcher = Some(substep_dispatcher);
+
+    let pipe_sprite = load_sprite("texture/pipe.png", "texture/pipe.ron", 0, world);
+    let bird_sprite = load_sprite("texture/bird.png", "texture/bird.ron", 0, world);
+    let particle_sprite = load_sprite("texture/particle.png", "texture/particle.ron", 0, world);
+    world.insert(ParticleSprite {
+      sprite: particle_sprite,
+    });
+    let coin_sprite = load_sprite("texture/coin.png", "texture/coin.ron", 0, world);
+    self.coin_sprite.replace(coin_sprite);
+    let ring_sprite = load_sprite("texture/ring.png", "texture/ring.ron", 0, world);
+    self.ring_sprite.replace(ring_sprite.clone());
+    world.insert(RhythmGuide {
+      sprite: ring_sprite,
+      ring: None,
+      phase: 0.,
+    });
+    self
+      .pipe_spawn_timer
+      .replace(world.read_resource::<GameConfig>().first_pipe_delay);
+    self.pipe_sprite.replace(pipe_sprite);
+    self.bird_sprite.replace(bird_sprite.clone());
+    self.reseed();
+    self.gap_source = load_gap_source(world);
+
+    let digits = world.read_resource::<GameConfig>().score_style == ScoreStyle::Digits;
+    if !digits && !asset_exists(SCORE_FONT) {
+      error!(
+        "Score font {} is missing, the score is drawn with sprite digits instead.",
+        SCORE_FONT
+      );
+    }
+    if digits || !asset_exists(SCORE_FONT) {
+      world.insert(ScoreDigits {
+        sprite: load_digit_sprites(world),
+        shown: String::new(),
+        digits: Vec::new(),
+      });
+    }
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(SCORE_FONT, TtfFormat, (), &world.read_resource());
+    let hud = world.read_resource::<GameConfig>().hud;
+
+    let text = world
+      .create_entity()
+      .with(hud.score.place(UiTransform::new(
+        "score".to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.,
+        -50.,
+        1.,
+        400.,
+        200.,
+      )))
+      .with(UiText::new(
+        font.clone(),
+        self.start_score(world),
+        // The digits read it from the invisible text.
+        [1., 1., 1., if digits { 0. } else { 1. }],
+        100.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .build();
+
+    world.insert(Score { text });
+
+    let hurry_up_text = world
+      .create_entity()
+      .with(UiTransform::new(
+        "hurry_up".to_string(),
+        Anchor::Middle,
+        Anchor::Middle,
+        0.,
+        0.,
+        1.,
+        800.,
+        200.,
+      ))
+      .with(UiText::new(
+        font.clone(),
+        "Hurry Up!".to_string(),
+        [1., 0.3, 0.3, 1.],
+        80.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .with(Hidden)
+      .build();
+
+    world.insert(HurryUpCue {
+      text: hurry_up_text,
+    });
+
+    let milestone_text = world
+      .create_entity()
+      .with(UiTransform::new(
+        "milestone".to_string(),
+        Anchor::Middle,
+        Anchor::Middle,
+        0.,
+        100.,
+        1.,
+        800.,
+        150.,
+      ))
+      .with(UiText::new(
+        font.clone(),
+        String::new(),
+        [1., 0.85, 0.3, 1.],
+        90.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .with(Hidden)
+      .build();
+    world.insert(Milestone {
+      text: milestone_text,
+      reached: 0,
+      remaining: 0.,
+    });
+
+    let gravity_flip_text = world
+      .create_entity()
+      .with(UiTransform::new(
+        "gravity_flip".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.,
+        60.,
+        1.,
+        800.,
+        60.,
+      ))
+      .with(UiText::new(
+        font.clone(),
+        String::new(),
+        [0.7, 0.3, 1., 1.],
+        40.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .with(Hidden)
+      .build();
+    world.insert(GravityFlip {
+      remaining: 0.,
+      text: gravity_flip_text,
+    });
+
+    let coins_text = world
+      .create_entity()
+      .with(hud.coins.place(UiTransform::new(
+        "coins".to_string(),
+        Anchor::TopRight,
+        Anchor::TopRight,
+        -30.,
+        -30.,
+        1.,
+        500.,
+        50.,
+      )))
+      .with(UiText::new(
+        font.clone(),
+        if self.mode == GameMode::Zen || self.mode == GameMode::Tutorial {
+          String::new()
+        } else {
+          "Coins: 0".to_string()
+        },
+        [1., 0.8, 0.15, 1.],
+        35.,
+        LineMode::Single,
+        Anchor::MiddleRight,
+      ))
+      .build();
+    world.insert(Coins { text: coins_text });
+
+    let noclip_text = world
+      .create_entity()
+      .with(UiTransform::new(
+        "noclip".to_string(),
+        Anchor::BottomRight,
+        Anchor::BottomRight,
+        -30.,
+        30.,
+        1.,
+        300.,
+        50.,
+      ))
+      .with(UiText::new(
+        font.clone(),
+        "NOCLIP".to_string(),
+        [1., 0.2, 0.2, 1.],
+        35.,
+        LineMode::Single,
+        Anchor::MiddleRight,
+      ))
+      .with(Hidden)
+      .build();
+    world.insert(NoClip {
+      active: false,
+      text: noclip_text,
+    });
+
+    let rewind_text = world
+      .create_entity()
+      .with(hud.coins.place(UiTransform::new(
+        "rewinds".to_string(),
+        Anchor::TopRight,
+        Anchor::TopRight,
+        -30.,
+        -80.,
+        1.,
+        500.,
+        50.,
+      )))
+      .with(UiText::new(
+        font.clone(),
+        String::new(),
+        [0.3, 1., 0.6, 1.],
+        35.,
+        LineMode::Single,
+        Anchor::MiddleRight,
+      ))
+      .build();
+    world.insert(Rewind {
+      charges: 0,
+      text: rewind_text,
+    });
+
+    let assist_text = {
+      let settings = world.read_resource::<Settings>();
+      if settings.assist_strength > 0. && self.mode != GameMode::Tutorial {
+        Some(format!("Flap Assist {}", settings.assist_label()))
+      } else {
+        None
+      }
+    };
+    self.assist_text = assist_text.map(|text| {
+      world
+        .create_entity()
+        .with(UiTransform::new(
+          "assist".to_string(),
+          Anchor::BottomLeft,
+          Anchor::BottomLeft,
+          30.,
+          30.,
+          1.,
+          500.,
+          50.,
+        ))
+        .with(UiText::new(
+          font.clone(),
+          text,
+          [0.6, 0.9, 1., 1.],
+          30.,
+          LineMode::Single,
+          Anchor::MiddleLeft,
+        ))
+        .build()
+    });
+
+    if self.mode == GameMode::Tutorial {
+      let text = world
+        .create_entity()
+        .with(UiTransform::new(
+          "tutorial".to_string(),
+          Anchor::Middle,
+          Anchor::Middle,
+          0.,
+          120.,
+          1.,
+          1000.,
+          60.,
+        ))
+        .with(UiText::new(
+          font.clone(),
+          TutorialStep::Flap.hint().to_string(),
+          [1., 1., 1., 1.],
+          40.,
+          LineMode::Single,
+          Anchor::Middle,
+        ))
+        .build();
+      self.tutorial = Some(Tutorial {
+        step: TutorialStep::Flap,
+        text,
+        flaps: 0,
+        elapsed: 0.,
+      });
+    }
+
+    let combo_text = world
+      .create_entity()
+      .with(hud.combo.place(UiTransform::new(
+        "combo".to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.,
+        -170.,
+        1.,
+        600.,
+        60.,
+      )))
+      .with(UiText::new(
+        font,
+        String::new(),
+        [1., 0.85, 0.3, 1.],
+        40.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .build();
+    let combo_meter = world
+      .create_entity()
+      .with(hud.combo.place(UiTransform::new(
+        "combo_meter".to_string(),
+        Anchor::TopMiddle,
+        Anchor::TopMiddle,
+        0.,
+        COMBO_METER_Y,
+        1.,
+        0.,
+        COMBO_METER_HEIGHT,
+      )))
+      .with(UiImage::SolidColor([1., 0.85, 0.3, 0.8]))
+      .build();
+    world.insert(Combo {
+      count: 0,
+      text: combo_text,
+      break_flash: 0.,
+      remaining: 0.,
+      meter: combo_meter,
+    });
+    world.insert(RunStats::default());
+    world.insert(PhysicsClock::default());
+    world.insert(ReplayBuffer::default());
+    world.insert(TrajectoryPreview {
+      shown: world.read_resource::<GameConfig>().beginner_hints,
+    });
+    reset_start_grace(world);
+    world.insert(self.mode);
+    if self.mode == GameMode::Zen {
+      set_score_font(world, "");
+    }
+
+    spawn_bird(world, bird_sprite);
+    apply_hud(world);
+    let mut shadowed = vec![
+      world.read_resource::<Score>().text,
+      hurry_up_text,
+      milestone_text,
+      gravity_flip_text,
+      coins_text,
+      noclip_text,
+      rewind_text,
+      combo_text,
+    ];
+    shadowed.extend(self.assist_text);
+    shadowed.extend(self.tutorial.as_ref().map(|tutorial| tutorial.text));
+    spawn_text_shadows(world, &shadowed);
+
+    if self.mode == GameMode::Tube {
+      self.tube_masks = spawn_tube_masks(world);
+      set_camera_rotation(world, -std::f32::consts::FRAC_PI_2);
+    }
+
+    if self.screenshot {
+      self.compose_screenshot(world);
+      world.write_resource::<HideUi>().hide = true;
+    }
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+    delete_run_entities(world);
+    let score = world.read_resource::<Score>().text;
+    let cue = world.read_resource::<HurryUpCue>().text;
+    let gravity_flip = world.read_resource::<GravityFlip>().text;
+    let (combo, combo_meter) = {
+      let combo = world.read_resource::<Combo>();
+      (combo.text, combo.meter)
+    };
+    let coins = world.read_resource::<Coins>().text;
+    let rewind = world.read_resource::<Rewind>().text;
+    let milestone = world.read_resource::<Milestone>().text;
+    let noclip = world.read_resource::<NoClip>().text;
+    world
+      .delete_entities(&[
+        score,
+        cue,
+        gravity_flip,
+        combo,
+        combo_meter,
+        coins,
+        rewind,
+        milestone,
+        noclip,
+      ])
+      .expect("Couldn't delete play state texts!");
+    let shadows: Vec<Entity> = (&world.entities(), &world.read_storage::<TextShadow>())
+      .join()
+      .map(|(e, _)| e)
+      .collect();
+    world
+      .delete_entities(&shadows)
+      .expect("Couldn't delete the text shadows!");
+    if let Some(tutorial) = self.tutorial.take() {
+      world
+        .delete_entity(tutorial.text)
+        .expect("Couldn't delete the tutorial hint!");
+    }
+    if let Some(text) = self.assist_text.take() {
+      world
+        .delete_entity(text)
+        .expect("Couldn't delete the assist text!");
+    }
+    if let Some((prompt, _)) = self.quit_prompt.take() {
+      world
+        .delete_entity(prompt)
+        .expect("Couldn't delete the quit prompt!");
+    }
+    if !self.tube_masks.is_empty() {
+      world
+        .delete_entities(&self.tube_masks)
+        .expect("Couldn't delete the tube masks!");
+      self.tube_masks.clear();
+      set_camera_rotation(world, 0.);
+    }
+    world.write_resource::<Profiler>().timings.clear();
+    world.write_resource::<ImpactPoint>().point = None;
+  }
+
+  fn on_pause(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    if !self.game_over {
+      return;
+    }
+
+    let world = data.world;
+
+    let result = self.run_result(world);
+    let last_score = set_score_font(world, "");
+    let completed = world.read_resource::<RunStats>().completed;
+    {
+      let (best_coins, new_best) = self.record_run(world);
+      world.insert(FinishedRun {
+        result,
+        mode: self.mode,
+        new_best,
+      });
+      let coins = world.read_resource::<RunStats>().coins;
+      if let Some(text) = world
+        .write_storage::<UiText>()
+        .get_mut(world.read_resource::<Coins>().text)
+      {
+        text.text = format!("Coins: {}  Best: {}", coins, best_coins);
+      }
+    }
+    clear_run(world);
+
+    let e_title = set_title_hidden(world, false);
+
+    let mut ui_text = world.write_storage::<UiText>();
+    if let Some(final_score_display) = e_title.and_then(|entity| ui_text.get_mut(entity)) {
+      final_score_display.text = if completed {
+        "Level Complete!".to_string()
+      } else {
+        format!("Your Score: {}", last_score)
+      };
+    }
+  }
+
+  fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    if data.world.remove::<RestartRequested>().is_some() {
+      self.reset_run(data.world);
+      return;
+    }
+    if !self.game_over {
+      // The key which left the pause menu, e.g. any key in one button mode, isn't a flap either.
+      if primary_action_held(data.world) {
+        for bird in (&mut data.world.write_storage::<Bird>()).join() {
+          bird.hold_until_released();
+        }
+      }
+      return;
+    }
+    self.game_over = false;
+
+    let world = data.world;
+
+    self.reset_run(world);
+
+    set_title_hidden(world, true);
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'_, '_>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_key_down(&event, VirtualKeyCode::Escape)
+        && (self.mode == GameMode::Zen || self.mode == GameMode::Tutorial)
+      {
+        return Trans::Pop;
+      }
+      if is_close_requested(&event) {
+        return self.quit(data.world);
+      }
+      if is_key_down(&event, VirtualKeyCode::Escape) {
+        return self.request_quit(data.world);
+      }
+      if is_key_down(&event, VirtualKeyCode::P) {
+        return Trans::Push(Box::new(PausedState::default()));
+      }
+      if is_key_down(&event, VirtualKeyCode::L)
+        && data.world.read_resource::<GameConfig>().beginner_hints
+      {
+        let mut preview = data.world.write_resource::<TrajectoryPreview>();
+        preview.shown = !preview.shown;
+      }
+      if cfg!(debug_assertions) && is_key_down(&event, VirtualKeyCode::F6) {
+        let world = &data.world;
+        let mut noclip = world.write_resource::<NoClip>();
+        noclip.active = !noclip.active;
+        if noclip.active {
+          world.write_resource::<RunStats>().noclip_used = true;
+          world.write_storage::<Hidden>().remove(noclip.text);
+        } else {
+          world
+            .write_storage::<Hidden>()
+            .insert(noclip.text, Hidden)
+            .expect("Error while trying to hide the noclip indicator!");
+        }
+      }
+      if is_key_down(&event, VirtualKeyCode::H) {
+        {
+          let mut settings = data.world.write_resource::<Settings>();
+          settings.hide_hud = !settings.hide_hud;
+          save_settings(&settings);
+        }
+        apply_hud(data.world);
+      }
+      if is_focus_lost(&event) && data.world.read_resource::<Settings>().auto_pause {
+        return Trans::Push(Box::new(PausedState {
+          by_focus_loss: true,
+          ..Default::default()
+        }));
+      }
+    }
+    if let MyStateEvent::Input(InputEvent::ControllerDisconnected { .. }) = event {
+      if data.world.read_resource::<GameConfig>().pause_on_disconnect {
+        return Trans::Push(Box::new(PausedState {
+          by_disconnect: true,
+          ..Default::default()
+        }));
+      }
+    }
+    if let Some(tutorial) = self.tutorial.as_mut() {
+      if is_primary_action(
+        &event,
+        data.world.read_resource::<Settings>().one_button_mode,
+      ) {
+        tutorial.flaps += 1;
+      }
+    }
+    if let MyStateEvent::Game(GameEvent::Collision(_)) = event {
+      if self.dying || self.rewound {
+        return Trans::None;
+      }
+      if self.mode != GameMode::Tutorial && self.rewind(data.world) {
+        return Trans::None;
+      }
+      {
+        let config = data.world.read_resource::<GameConfig>();
+        if config.flash_enabled {
+          if let Some(mut flash) = data.world.try_fetch_mut::<Flash>() {
+            flash.alpha = config.flash_peak_alpha;
+          }
+        }
+        data.world.write_resource::<Shake>().remaining = SHAKE_DURATION;
+      }
+      if self.mode == GameMode::Tutorial {
+        self.retry_tutorial_step(data.world);
+        return Trans::None;
+      }
+      if data.world.read_resource::<GameConfig>().death_fall {
+        self.dying = true;
+        return Trans::None;
+      }
+      return self.end_run(data.world);
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if data.world.remove::<QuitRequested>().is_some() {
+      return self.quit(data.world);
+    }
+    if data.world.remove::<QuitToTitleRequested>().is_some() {
+      self.count_run(data.world);
+      return Trans::Pop;
+    }
+    if self.game_over_pending {
+      self.game_over_pending = false;
+      self.game_over = true;
+      return Trans::Push(Box::new(GameOverState::default()));
+    }
+    if let Some((prompt, remaining)) = self.quit_prompt.as_mut() {
+      *remaining -= data.world.read_resource::<Time>().delta_real_seconds();
+      if *remaining <= 0. {
+        data
+          .world
+          .delete_entity(*prompt)
+          .expect("Couldn't delete the quit prompt!");
+        self.quit_prompt = None;
+      }
+    }
+    if self.screenshot {
+      // Only the first run is staged, retries are regular runs again.
+      self.screenshot = false;
+      data.data.update(&data.world);
+      return Trans::Push(Box::new(PausedState::default()));
+    }
+
+    let steps = {
+      let delta = data.world.fetch::<Time>().delta_seconds();
+      data.world.write_resource::<PhysicsClock>().advance(delta)
+    };
+    if self.dying {
+      let landed = self.animate_death(data.world, steps);
+      data.data.update(&data.world);
+      if landed {
+        self.dying = false;
+        return self.end_run(data.world);
+      }
+      return Trans::None;
+    }
+    self.rewound = false;
+    for _ in 0..steps {
+      {
+        let mut grace = data.world.write_resource::<StartGrace>();
+        grace.remaining = (grace.remaining - PHYSICS_STEP).max(0.);
+      }
+      self.spawn_pipes(data.world);
+      let substeps = {
+        let config = data.world.read_resource::<GameConfig>();
+        (&data.world.read_storage::<Bird>())
+          .join()
+          .map(|bird| config.substeps_for(bird.dy))
+          .max()
+          .unwrap_or(1)
+      };
+      {
+        let mut clock = data.world.write_resource::<PhysicsClock>();
+        clock.substeps = substeps;
+        clock.substep = 0;
+      }
+      if let Some(dispatcher) = self.dispatcher.as_mut() {
+        dispatcher.dispatch(&data.world);
+      }
+      for substep in 1..substeps {
+        // The bird already hit something, moving it on would only report it again.
+        if data.world.read_resource::<ImpactPoint>().point.is_some() {
+          break;
+        }
+        data.world.write_resource::<PhysicsClock>().substep = substep;
+        if let Some(dispatcher) = self.substep_dispatcher.as_mut() {
+          dispatcher.dispatch(&data.world);
+        }
+      }
+      if let Some(frame) = data
+        .world
+        .write_resource::<ReplayBuffer>()
+        .frames
+        .back_mut()
+      {
+        frame.spawn_timer = self.pipe_spawn_timer;
+        frame.next_gap = self.next_gap;
+      }
+      if data.world.read_resource::<RunStats>().completed {
+        break;
+      }
+    }
+    TrajectorySystem.run_now(&data.world);
+    HitboxSystem.run_now(&data.world);
+    data.data.update(&data.world);
+    if data.world.read_resource::<RunStats>().completed {
+      return self.end_run(data.world);
+    }
+    if self.level_exhausted() && (&data.world.read_storage::<Pipe>()).join().next().is_none() {
+      return self.end_run(data.world);
+    }
+    if self.advance_tutorial(data.world) {
+      let mut settings = data.world.write_resource::<Settings>();
+      settings.tutorial_completed = true;
+      save_settings(&settings);
+      return Trans::Switch(Box::new(PlayState::default()));
+    }
+    Trans::None
+  }
+}
+
+/// Every entry of the settings menu in display order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SettingsEntry {
+  FrameCap,
+  OneButtonMode,
+  FlapAssist,
+  AutoPause,
+  RhythmGuide,
+  BirdTint,
+  Tutorial,
+  /// Not a setting, confirming it opens the `SoundTestState`.
+  SoundTest,
+}
+
+const SETTINGS_ENTRIES: &[SettingsEntry] = &[
+  SettingsEntry::FrameCap,
+  SettingsEntry::OneButtonMode,
+  SettingsEntry::FlapAssist,
+  SettingsEntry::AutoPause,
+  SettingsEntry::RhythmGuide,
+  SettingsEntry::BirdTint,
+  SettingsEntry::Tutorial,
+  SettingsEntry::SoundTest,
+];
+
+impl SettingsEntry {
+  fn label(self, settings: &Settings) -> String {
+    match self {
+      SettingsEntry::FrameCap => format!("Frame Cap: {}", settings.frame_cap.label()),
+      SettingsEntry::OneButtonMode => format!("One Button: {}", on_off(settings.one_button_mode)),
+      SettingsEntry::FlapAssist => format!("Flap Assist: {}", settings.assist_label()),
+      SettingsEntry::AutoPause => format!("Pause On Focus Loss: {}", on_off(settings.auto_pause)),
+      SettingsEntry::RhythmGuide => format!("Rhythm Guide: {}", on_off(settings.rhythm_guide)),
+      SettingsEntry::BirdTint => format!("Bird Color: {}", settings.bird_tint_label()),
+      SettingsEntry::Tutorial => format!(
+        "Tutorial: {}",
+        if settings.tutorial_completed {
+          "Done"
+        } else {
+          "Next Run"
+        }
+      ),
+      SettingsEntry::SoundTest => "Sound Test".to_string(),
+    }
+  }
+
+  /// Changes the setting one step forward or backward and applies it right away.
+  fn change(self, world: &mut World, forward: bool) {
+    match self {
+      SettingsEntry::FrameCap => {
+        let frame_cap = {
+          let mut settings = world.write_resource::<Settings>();
+          settings.frame_cap = if forward {
+            settings.frame_cap.next()
+          } else {
+            settings.frame_cap.previous()
+          };
+          settings.frame_cap
+        };
+        let (strategy, fps) = frame_cap.limit();
+        world
+          .write_resource::<FrameLimiter>()
+          .set_rate(strategy, fps);
+      }
+      SettingsEntry::OneButtonMode => {
+        let mut settings = world.write_resource::<Settings>();
+        settings.one_button_mode = !settings.one_button_mode;
+      }
+      SettingsEntry::FlapAssist => {
+        world.write_resource::<Settings>().cycle_assist(forward);
+      }
+      SettingsEntry::AutoPause => {
+        let mut settings = world.write_resource::<Settings>();
+        settings.auto_pause = !settings.auto_pause;
+      }
+      SettingsEntry::RhythmGuide => {
+        let mut settings = world.write_resource::<Settings>();
+        settings.rhythm_guide = !settings.rhythm_guide;
+      }
+      SettingsEntry::BirdTint => {
+        world.write_resource::<Settings>().cycle_bird_tint(forward);
+      }
+      SettingsEntry::Tutorial => {
+        let mut settings = world.write_resource::<Settings>();
+        settings.tutorial_completed = !settings.tutorial_completed;
+      }
+      SettingsEntry::SoundTest => {}
+    }
+  }
+}
+
+#[derive(Default)]
+struct SettingsState {
+  selected: usize,
+  texts: Vec<Entity>,
+}
+
+impl SettingsState {
+  fn refresh(&self, world: &World) {
+    let settings = world.read_resource::<Settings>();
+    let mut ui_text = world.write_storage::<UiText>();
+    for (i, (entry, entity)) in SETTINGS_ENTRIES.iter().zip(&self.texts).enumerate() {
+      if let Some(text) = ui_text.get_mut(*entity) {
+        let marker = if i == self.selected { "> " } else { "" };
+        text.text = format!("{}{}", marker, entry.label(&settings));
+      }
+    }
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for SettingsState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(MENU_FONT, TtfFormat, (), &world.read_resource());
+
+    for i in 0..SETTINGS_ENTRIES.len() {
+      let text = world
+        .create_entity()
+        .with(UiTransform::new(
+          format!("settings_{}", i),
+          Anchor::Middle,
+          Anchor::Middle,
+          0.,
+          160. - i as f32 * 60.,
+          1.,
+          1100.,
+          60.,
+        ))
+        .with(UiText::new(
+          font.clone(),
+          String::new(),
+          [1., 1., 1., 1.],
+          50.,
+          LineMode::Single,
+          Anchor::Middle,
+        ))
+        .build();
+      self.texts.push(text);
+    }
+
+    self.refresh(world);
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    save_settings(&data.world.read_resource::<Settings>());
+    data
+      .world
+      .delete_entities(&self.texts)
+      .expect("Couldn't delete settings menu entities!");
+    self.texts.clear();
+  }
+
+  fn on_pause(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let mut hidden = data.world.write_storage::<Hidden>();
+    for text in &self.texts {
+      hidden
+        .insert(*text, Hidden)
+        .expect("Error while trying to hide settings menu!");
+    }
+  }
+
+  fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let mut hidden = data.world.write_storage::<Hidden>();
+    for text in &self.texts {
+      hidden.remove(*text);
+    }
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) {
+        return Trans::Quit;
+      }
+      if is_key_down(&event, VirtualKeyCode::Escape) {
+        return Trans::Pop;
+      }
+      if is_key_down(&event, VirtualKeyCode::Up) {
+        self.selected = (self.selected + SETTINGS_ENTRIES.len() - 1) % SETTINGS_ENTRIES.len();
+      } else if is_key_down(&event, VirtualKeyCode::Down) {
+        self.selected = (self.selected + 1) % SETTINGS_ENTRIES.len();
+      } else if (is_key_down(&event, VirtualKeyCode::Right)
+        || is_key_down(&event, VirtualKeyCode::Return))
+        && SETTINGS_ENTRIES[self.selected] == SettingsEntry::SoundTest
+      {
+        return Trans::Push(Box::new(SoundTestState::default()));
+      } else if is_key_down(&event, VirtualKeyCode::Right)
+        || is_key_down(&event, VirtualKeyCode::Return)
+      {
+        SETTINGS_ENTRIES[self.selected].change(data.world, true);
+      } else if is_key_down(&event, VirtualKeyCode::Left) {
+        SETTINGS_ENTRIES[self.selected].change(data.world, false);
+      }
+      self.refresh(data.world);
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    data.data.update(&data.world);
+    Trans::None
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum SoundTestEntry {
+  Score,
+  Hurt,
+  Explosion,
+  Jump,
+  Whoosh,
+  HurryUp,
+  Tick,
+  ComboLost,
+  Coin,
+  GroundHit,
+  PipeHit,
+  CeilingHit,
+  Milestone,
+  Music(usize),
+}
+
+impl SoundTestEntry {
+  fn all(music_tracks: usize) -> Vec<SoundTestEntry> {
+    let mut entries = vec![
+      SoundTestEntry::Score,
+      SoundTestEntry::Hurt,
+      SoundTestEntry::Explosion,
+      SoundTestEntry::Jump,
+      SoundTestEntry::Whoosh,
+      SoundTestEntry::HurryUp,
+      SoundTestEntry::Tick,
+      SoundTestEntry::ComboLost,
+      SoundTestEntry::Coin,
+      SoundTestEntry::GroundHit,
+      SoundTestEntry::PipeHit,
+      SoundTestEntry::CeilingHit,
+      SoundTestEntry::Milestone,
+    ];
+    entries.extend((0..music_tracks).map(SoundTestEntry::Music));
+    entries
+  }
+
+  fn label(self, tracks: &[String]) -> String {
+    match self {
+      SoundTestEntry::Score => "Score".to_string(),
+      SoundTestEntry::Hurt => "Hurt".to_string(),
+      SoundTestEntry::Explosion => "Explosion".to_string(),
+      SoundTestEntry::Jump => "Jump".to_string(),
+      SoundTestEntry::Whoosh => "Whoosh".to_string(),
+      SoundTestEntry::HurryUp => "Hurry Up".to_string(),
+      SoundTestEntry::Tick => "Rhythm Tick".to_string(),
+      SoundTestEntry::ComboLost => "Combo Lost".to_string(),
+      SoundTestEntry::Coin => "Coin".to_string(),
+      SoundTestEntry::GroundHit => "Ground Hit".to_string(),
+      SoundTestEntry::PipeHit => "Pipe Hit".to_string(),
+      SoundTestEntry::CeilingHit => "Ceiling Hit".to_string(),
+      SoundTestEntry::Milestone => "Milestone".to_string(),
+      SoundTestEntry::Music(i) => format!("Music: {}", tracks[i]),
+    }
+  }
+}
+
+/// Lists every sound effect and music track so their volumes can be compared.
+#[derive(Default)]
+struct SoundTestState {
+  entries: Vec<SoundTestEntry>,
+  selected: usize,
+  texts: Vec<Entity>,
+  tracks: Vec<String>,
+  music: Vec<SourceHandle>,
+  /// Plays the selected music track on its own, dropping it stops the track.
+  preview: Option<AudioSink>,
+  /// Set while a preview holds the DJ's music, it's picked up where it stopped.
+  music_paused: bool,
+}
+
+impl SoundTestState {
+  fn refresh(&self, world: &World) {
+    let mut ui_text = world.write_storage::<UiText>();
+    for (i, (entry, entity)) in self.entries.iter().zip(&self.texts).enumerate() {
+      if let Some(text) = ui_text.get_mut(*entity) {
+        let marker = if i == self.selected { "> " } else { "" };
+        text.text = format!("{}{}", marker, entry.label(&self.tracks));
+      }
+    }
+  }
+
+  /// Goes through the same helpers the game uses so the volumes match what's heard while playing.
+  fn play(&mut self, world: &World) {
+    let sounds = world.read_resource::<Sounds>();
+    let storage = world.read_resource::<AssetStorage<Source>>();
+    let output = world.try_fetch::<Output>();
+    let output = output.as_deref();
+    let entry = self.entries[self.selected];
+    match entry {
+      SoundTestEntry::Score => play_score_sound(&sounds, None, &storage, output),
+      SoundTestEntry::Hurt => play_sound(&sounds.hurt_sfx, 0.25, &storage, output),
+      SoundTestEntry::Explosion => play_sound(&sounds.explosion_sfx, 0.25, &storage, output),
+      SoundTestEntry::Jump => play_jump_sound(&sounds, None, &storage, output),
+      SoundTestEntry::Whoosh => play_whoosh_sound(&sounds, &storage, output),
+      SoundTestEntry::HurryUp => play_hurry_up_sound(&sounds, &storage, output),
+      SoundTestEntry::Tick => play_tick_sound(&sounds, &storage, output),
+      SoundTestEntry::ComboLost => play_combo_lost_sound(&sounds, &storage, output),
+      SoundTestEntry::Coin => play_coin_sound(&sounds, &storage, output),
+      SoundTestEntry::GroundHit => play_sound(&sounds.ground_hit_sfx, 0.25, &storage, output),
+      SoundTestEntry::PipeHit => play_sound(&sounds.pipe_hit_sfx, 0.25, &storage, output),
+      SoundTestEntry::CeilingHit => play_sound(&sounds.ceiling_hit_sfx, 0.25, &storage, output),
+      SoundTestEntry::Milestone => play_sound(&sounds.milestone_sfx, 0.3, &storage, output),
+      SoundTestEntry::Music(i) => self.preview_music(world, i),
+    }
+  }
+
+  /// Replaces the playing preview with `track` at the music volume, the DJ holds its own track
+  /// meanwhile.
+  fn preview_music(&mut self, world: &World, track: usize) {
+    let output = match world.try_fetch::<Output>() {
+      Some(output) => output,
+      None => return,
+    };
+    let storage = world.read_resource::<AssetStorage<Source>>();
+    let source = match storage.get(&self.music[track]) {
+      Some(source) => source,
+      None => return,
+    };
+    let mut volume = 1.;
+    if let Some(sink) = world.try_fetch::<AudioSink>() {
+      if !sink.is_paused() {
+        sink.pause();
+        self.music_paused = true;
+      }
+      volume = sink.volume();
+    }
+    let preview = AudioSink::new(&output);
+    preview.set_volume(volume);
+    match preview.append(source) {
+      Ok(()) => self.preview = Some(preview),
+      Err(e) => warn!("Couldn't play {}: {}", self.tracks[track], e),
+    }
+  }
+
+  fn stop_preview(&mut self, world: &World) {
+    self.preview = None;
+    if self.music_paused {
+      self.music_paused = false;
+      if let Some(sink) = world.try_fetch::<AudioSink>() {
+        sink.play();
+      }
+    }
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for SoundTestState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+    self.tracks = world.read_resource::<Music>().tracks.clone();
+    self.entries = SoundTestEntry::all(self.tracks.len());
+    self.music = {
+      let loader = world.read_resource::<Loader>();
+      self
+        .tracks
+        .iter()
+        .map(|file| load_music_track(&loader, &world, file, ()))
+        .collect()
+    };
+
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(MENU_FONT, TtfFormat, (), &world.read_resource());
+
+    // Every music track gets an entry, so the list shrinks to fit the screen.
+    let spacing = (600. / self.entries.len() as f32).min(50.);
+    for i in 0..self.entries.len() {
+      let text = world
+        .create_entity()
+        .with(UiTransform::new(
+          format!("sound_test_{}", i),
+          Anchor::Middle,
+          Anchor::Middle,
+          0.,
+          300. - spacing / 2. - i as f32 * spacing,
+          1.,
+          1100.,
+          spacing,
+        ))
+        .with(UiText::new(
+          font.clone(),
+          String::new(),
+          [1., 1., 1., 1.],
+          spacing * 0.8,
+          LineMode::Single,
+          Anchor::Middle,
+        ))
+        .build();
+      self.texts.push(text);
+    }
+
+    self.refresh(world);
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    data
+      .world
+      .delete_entities(&self.texts)
+      .expect("Couldn't delete sound test entities!");
+    self.texts.clear();
+    self.stop_preview(data.world);
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) {
+        return Trans::Quit;
+      }
+      if is_key_down(&event, VirtualKeyCode::Escape) {
+        return Trans::Pop;
+      }
+      if is_key_down(&event, VirtualKeyCode::Up) {
+        self.selected = (self.selected + self.entries.len() - 1) % self.entries.len();
+      } else if is_key_down(&event, VirtualKeyCode::Down) {
+        self.selected = (self.selected + 1) % self.entries.len();
+      } else if is_key_down(&event, VirtualKeyCode::Return) {
+        self.play(data.world);
+      }
+      self.refresh(data.world);
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    // A finished preview gives the music back to the DJ.
+    if self
+      .preview
+      .as_ref()
+      .map_or(false, |preview| preview.empty())
+    {
+      self.stop_preview(data.world);
+    }
+    data.data.update(&data.world);
+    Trans::None
+  }
+}
+
+/// Picks the profile whose records are kept, each one is listed with its bests.
+#[derive(Default)]
+struct ProfileState {
+  selected: usize,
+  texts: Vec<Entity>,
+  best_coins: Vec<u32>,
+}
+
+impl ProfileState {
+  fn refresh(&self, world: &World) {
+    let active = world.read_resource::<Settings>().profile;
+    let mut ui_text = world.write_storage::<UiText>();
+    for (i, (best_coins, entity)) in self.best_coins.iter().zip(&self.texts).enumerate() {
+      if let Some(text) = ui_text.get_mut(*entity) {
+        let marker = if i == self.selected { "> " } else { "" };
+        let active = if i == active { " (active)" } else { "" };
+        text.text = format!(
+          "{}Profile {}{}  Best Coins: {}",
+          marker,
+          i + 1,
+          active,
+          best_coins
+        );
+      }
+    }
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for ProfileState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+    self.selected = world.read_resource::<Settings>().profile;
+    self.best_coins = (0..PROFILE_COUNT)
+      .map(|profile| load_records(profile).best_coins)
+      .collect();
+
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(MENU_FONT, TtfFormat, (), &world.read_resource());
+
+    for i in 0..PROFILE_COUNT {
+      let text = world
+        .create_entity()
+        .with(UiTransform::new(
+          format!("profile_{}", i),
+          Anchor::Middle,
+          Anchor::Middle,
+          0.,
+          60. - i as f32 * 60.,
+          1.,
+          1100.,
+          60.,
+        ))
+        .with(UiText::new(
+          font.clone(),
+          String::new(),
+          [1., 1., 1., 1.],
+          50.,
+          LineMode::Single,
+          Anchor::Middle,
+        ))
+        .build();
+      self.texts.push(text);
+    }
+
+    self.refresh(world);
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    data
+      .world
+      .delete_entities(&self.texts)
+      .expect("Couldn't delete profile menu entities!");
+    self.texts.clear();
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) {
+        return Trans::Quit;
+      }
+      if is_key_down(&event, VirtualKeyCode::Escape) {
+        return Trans::Pop;
+      }
+      if is_key_down(&event, VirtualKeyCode::Up) {
+        self.selected = (self.selected + PROFILE_COUNT - 1) % PROFILE_COUNT;
+      } else if is_key_down(&event, VirtualKeyCode::Down) {
+        self.selected = (self.selected + 1) % PROFILE_COUNT;
+      } else if is_key_down(&event, VirtualKeyCode::Return) {
+        let world = data.world;
+        {
+          let mut settings = world.write_resource::<Settings>();
+          settings.profile = self.selected;
+          save_settings(&settings);
+        }
+        world.insert(load_records(self.selected));
+        return Trans::Pop;
+      }
+      self.refresh(data.world);
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    data.data.update(&data.world);
+    Trans::None
+  }
+}
+
+/// Plays the recorded frames before a death back in slow motion, any key skips it.
+struct DeathReplayState {
+  frames: VecDeque<ReplayFrame>,
+  cursor: f32,
+}
+
+impl DeathReplayState {
+  fn new(frames: VecDeque<ReplayFrame>) -> Self {
+    DeathReplayState { frames, cursor: 0. }
+  }
+
+  /// Pipes and pickups, everything a frame records besides the bird.
+  fn replayed_entities(world: &World) -> Vec<Entity> {
+    let pipes = world.read_storage::<Pipe>();
+    let power_ups = world.read_storage::<PowerUp>();
+    let coins = world.read_storage::<Coin>();
+    let wells = world.read_storage::<GravityWell>();
+    (&world.entities())
+      .join()
+      .filter(|e| {
+        pipes.contains(*e) || power_ups.contains(*e) || coins.contains(*e) || wells.contains(*e)
+      })
+      .collect()
+  }
+
+  fn show_frame(&self, world: &World, frame: &ReplayFrame) {
+    let birds = world.read_storage::<Bird>();
+    let mut transforms = world.write_storage::<Transform>();
+    let mut hidden = world.write_storage::<Hidden>();
+
+    for (_, transform) in (&birds, &mut transforms).join() {
+      transform.set_translation(frame.bird);
+    }
+
+    // Pickups grabbed since the frame are gone for good, the ones spawned after it are hidden.
+    for e in Self::replayed_entities(world) {
+      let mut saved = frame.pipes.iter().chain(frame.pickups.iter());
+      match saved.find(|(entity, _)| *entity == e) {
+        Some((_, translation)) => {
+          if let Some(transform) = transforms.get_mut(e) {
+            transform.set_translation(*translation);
+          }
+          hidden.remove(e);
+        }
+        None => {
+          hidden
+            .insert(e, Hidden)
+            .expect("Error while trying to hide an entity during replay!");
+        }
+      }
+    }
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for DeathReplayState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    if let Some(frame) = self.frames.front() {
+      self.show_frame(data.world, frame);
+    }
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let mut hidden = data.world.write_storage::<Hidden>();
+    for e in Self::replayed_entities(data.world) {
+      hidden.remove(e);
+    }
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) {
+        data.world.insert(QuitRequested);
+        return Trans::Pop;
+      }
+    }
+    match event {
+      MyStateEvent::Input(InputEvent::KeyPressed { .. })
+      | MyStateEvent::Input(InputEvent::MouseButtonPressed(_)) => Trans::Pop,
+      _ => Trans::None,
+    }
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    self.cursor += data.world.fetch::<Time>().delta_seconds() * DEATH_REPLAY_SPEED / PHYSICS_STEP;
+    let trans = match self.frames.get(self.cursor as usize) {
+      Some(frame) => {
+        self.show_frame(data.world, frame);
+        Trans::None
+      }
+      None => Trans::Pop,
+    };
+    data.data.update(&data.world);
+    trans
+  }
+}
+
+/// Shows the logo at launch for `GameConfig::splash_duration`, any key skips it.
+#[derive(Default)]
+struct SplashState {
+  remaining: f32,
+  logo: Option<Entity>,
+  /// Handed on to the `TitleScreenState`, a screenshot run skips the splash.
+  screenshot: bool,
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for SplashState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+
+    init_camera(world);
+
+    self.remaining = if self.screenshot {
+      0.
+    } else {
+      world.read_resource::<GameConfig>().splash_duration
+    };
+    if self.remaining > 0. {
+      let sprite = load_sprite("texture/logo.png", "texture/logo.ron", 0, world);
+      let mut transform = Transform::from(Vector3::new(0., 0., Z_LOGO));
+      transform.set_scale(Vector3::new(2., 2., 1.));
+      self.logo = Some(world.create_entity().with(sprite).with(transform).build());
+    }
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    if let Some(logo) = self.logo.take() {
+      data
+        .world
+        .delete_entity(logo)
+        .expect("Couldn't delete the splash logo!");
+    }
+  }
+
+  fn handle_event(
+    &mut self,
+    _data: StateData<'_, GameData<'_, '_>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+        return Trans::Quit;
+      }
+    }
+    if is_primary_action(&event, true) {
+      self.remaining = 0.;
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    data.data.update(&data.world);
+    self.remaining -= data.world.fetch::<Time>().delta_seconds();
+    if self.remaining <= 0. {
+      return Trans::Switch(Box::new(TitleScreenState {
+        screenshot: self.screenshot,
+        ..Default::default()
+      }));
+    }
+    Trans::None
+  }
+}
+
+/// Entries of the pause menu in display order.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PauseEntry {
+  Resume,
+  Restart,
+  QuitToTitle,
+}
+
+const PAUSE_ENTRIES: &[PauseEntry] = &[
+  PauseEntry::Resume,
+  PauseEntry::Restart,
+  PauseEntry::QuitToTitle,
+];
+
+impl PauseEntry {
+  /// Id of its label in `PAUSE_LAYOUT`.
+  fn id(self) -> &'static str {
+    match self {
+      PauseEntry::Resume => "pause_resume",
+      PauseEntry::Restart => "pause_restart",
+      PauseEntry::QuitToTitle => "pause_quit",
+    }
+  }
+
+  fn label(self) -> &'static str {
+    match self {
+      PauseEntry::Resume => "Resume",
+      PauseEntry::Restart => "Restart",
+      PauseEntry::QuitToTitle => "Quit To Title",
+    }
+  }
+}
+
+/// Left behind by the pause menu for the `PlayState` to start over once it's resumed.
+struct RestartRequested;
+
+/// Left behind by the pause menu and the death replay for the `PlayState` to count the run and
+/// quit once it's resumed, the quit was confirmed already.
+struct QuitRequested;
+
+/// Left behind by the pause menu for the `PlayState` to count the run and go back to the title
+/// once it's resumed.
+struct QuitToTitleRequested;
+
+/// Lists the `Leaderboards` of the active profile, one tab per mode in `RANKED_MODES`.
+#[derive(Default)]
+struct LeaderboardState {
+  menu: Option<Entity>,
+  tab: usize,
+}
+
+impl LeaderboardState {
+  /// The labels only exist once `LEADERBOARD_LAYOUT` is loaded, so this runs every frame.
+  fn refresh(&self, world: &mut World) {
+    let mode = RANKED_MODES[self.tab];
+    let score_format = world.read_resource::<GameConfig>().score_format;
+    let rows: Vec<String> = {
+      let records = world.read_resource::<Records>();
+      let table = records.leaderboards.table(mode);
+      (0..LEADERBOARD_SIZE)
+        .map(|i| match table.get(i) {
+          Some(entry) => format!(
+            "{}. {}   {}",
+            i + 1,
+            format_score(entry.score, score_format),
+            format_date(entry.date)
+          ),
+          None => format!("{}. -", i + 1),
+        })
+        .collect()
+    };
+    world.exec(|(finder, mut ui_text): (UiFinder, WriteStorage<UiText>)| {
+      if let Some(text) = finder
+        .find("leaderboard_tab")
+        .and_then(|e| ui_text.get_mut(e))
+      {
+        text.text = format!("< {} >", mode.label());
+      }
+      for (i, row) in rows.into_iter().enumerate() {
+        let id = format!("leaderboard_{}", i);
+        if let Some(text) = finder.find(&id).and_then(|e| ui_text.get_mut(e)) {
+          text.text = row;
+        }
+      }
+    });
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for LeaderboardState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let menu = data
+      .world
+      .exec(|mut creator: UiCreator<'_>| creator.create(LEADERBOARD_LAYOUT, ()));
+    self.menu.replace(menu);
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    if let Some(menu) = self.menu.take() {
+      let world = data.world;
+      let hierarchy = world.read_resource::<ParentHierarchy>();
+      let entities = world.entities();
+      for e in hierarchy.all_children_iter(menu) {
+        entities
+          .delete(e)
+          .expect("Couldn't delete leaderboard widget!");
+      }
+      entities.delete(menu).expect("Couldn't delete leaderboard!");
+    }
+  }
+
+  fn handle_event(
+    &mut self,
+    _data: StateData<'_, GameData<'a, 'b>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) {
+        return Trans::Quit;
+      }
+      if is_key_down(&event, VirtualKeyCode::Escape) || is_key_down(&event, VirtualKeyCode::Return)
+      {
+        return Trans::Pop;
+      }
+      if is_key_down(&event, VirtualKeyCode::Left) {
+        self.tab = (self.tab + RANKED_MODES.len() - 1) % RANKED_MODES.len();
+      }
+      if is_key_down(&event, VirtualKeyCode::Right) {
+        self.tab = (self.tab + 1) % RANKED_MODES.len();
+      }
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    self.refresh(data.world);
+    data.data.update(&data.world);
+    Trans::None
+  }
+}
+
+/// Freezes a running game, the `PlayState` below doesn't get any updates until it's popped.
+#[derive(Default)]
+struct PausedState {
+  by_focus_loss: bool,
+  by_disconnect: bool,
+  /// Asks to reconnect the gamepad while it's `by_disconnect`.
+  disconnect_prompt: Option<Entity>,
+  menu: Option<Entity>,
+  selected: usize,
+  /// Set while `GameConfig::pause_music` holds the music, it's picked up where it stopped.
+  music_paused: bool,
+  /// Asks for a second `Escape` with `GameConfig::confirm_quit`, the game is frozen anyway so
+  /// it stays up.
+  quit_prompt: Option<Entity>,
+}
+
+impl PausedState {
+  /// The labels only exist once `PAUSE_LAYOUT` is loaded, so this runs every frame.
+  fn refresh(&self, world: &mut World) {
+    let selected = self.selected;
+    world.exec(|(finder, mut ui_text): (UiFinder, WriteStorage<UiText>)| {
+      for (i, entry) in PAUSE_ENTRIES.iter().enumerate() {
+        if let Some(text) = finder.find(entry.id()).and_then(|e| ui_text.get_mut(e)) {
+          let marker = if i == selected { "> " } else { "" };
+          text.text = format!("{}{}", marker, entry.label());
+        }
+      }
+    });
+  }
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for PausedState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let menu = data
+      .world
+      .exec(|mut creator: UiCreator<'_>| creator.create(PAUSE_LAYOUT, ()));
+    self.menu.replace(menu);
+    if self.by_disconnect {
+      let font = data.world.read_resource::<Loader>().load(
+        MENU_FONT,
+        TtfFormat,
+        (),
+        &data.world.read_resource(),
+      );
+      let prompt = data
+        .world
+        .create_entity()
+        .with(UiTransform::new(
+          "pause_disconnect".to_string(),
+          Anchor::BottomMiddle,
+          Anchor::BottomMiddle,
+          0.,
+          30.,
+          6.,
+          800.,
+          40.,
+        ))
+        .with(UiText::new(
+          font,
+          "Controller disconnected, reconnect it or press P".to_string(),
+          [1., 1., 1., 1.],
+          28.,
+          LineMode::Single,
+          Anchor::Middle,
+        ))
+        .build();
+      self.disconnect_prompt = Some(prompt);
+    }
+    if data.world.read_resource::<GameConfig>().pause_music {
+      // Without an audio device there's no sink and nothing to pause.
+      if let Some(sink) = data.world.try_fetch::<AudioSink>() {
+        if !sink.is_paused() {
+          sink.pause();
+          self.music_paused = true;
+        }
+      }
+    }
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    if let Some(prompt) = self.disconnect_prompt.take() {
+      data
+        .world
+        .delete_entity(prompt)
+        .expect("Couldn't delete the disconnect prompt!");
+    }
+    if let Some(prompt) = self.quit_prompt.take() {
+      data
+        .world
+        .delete_entity(prompt)
+        .expect("Couldn't delete the quit prompt!");
+    }
+    if self.music_paused {
+      self.music_paused = false;
+      if let Some(sink) = data.world.try_fetch::<AudioSink>() {
+        sink.play();
+      }
+    }
+    if let Some(menu) = self.menu.take() {
+      let world = data.world;
+      let hierarchy = world.read_resource::<ParentHierarchy>();
+      let entities = world.entities();
+      for e in hierarchy.all_children_iter(menu) {
+        entities
+          .delete(e)
+          .expect("Couldn't delete pause menu widget!");
+      }
+      entities.delete(menu).expect("Couldn't delete pause menu!");
+    }
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+    event: MyStateEvent,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    if let MyStateEvent::Window(event) = &event {
+      let confirm_quit = data.world.read_resource::<GameConfig>().confirm_quit;
+      if is_key_down(&event, VirtualKeyCode::Escape) && confirm_quit && self.quit_prompt.is_none() {
+        // Below the last entry of the menu.
+        self.quit_prompt = Some(PlayState::spawn_quit_prompt(data.world, -200.));
+        return Trans::None;
+      }
+      if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+        data.world.insert(QuitRequested);
+        return Trans::Pop;
+      }
+      if is_key_down(&event, VirtualKeyCode::P) || (self.by_focus_loss && is_focus_gained(&event)) {
+        return Trans::Pop;
+      }
+      if is_key_down(&event, VirtualKeyCode::Up) {
+        self.selected = (self.selected + PAUSE_ENTRIES.len() - 1) % PAUSE_ENTRIES.len();
+      }
+      if is_key_down(&event, VirtualKeyCode::Down) {
+        self.selected = (self.selected + 1) % PAUSE_ENTRIES.len();
+      }
+      if is_key_down(&event, VirtualKeyCode::Return) {
+        match PAUSE_ENTRIES[self.selected] {
+          PauseEntry::Resume => return Trans::Pop,
+          PauseEntry::Restart => {
+            data.world.insert(RestartRequested);
+            return Trans::Pop;
+          }
+          PauseEntry::QuitToTitle => {
+            data.world.insert(QuitToTitleRequested);
+            return Trans::Pop;
+          }
+        }
+      }
+    }
+    if let MyStateEvent::Input(InputEvent::ControllerConnected { .. }) = event {
+      if self.by_disconnect {
+        return Trans::Pop;
+      }
+    }
+    Trans::None
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
+    self.refresh(data.world);
+    data.data.update(&data.world);
+    Trans::None
+  }
+}
+
+/// With `GameConfig::game_over_timeout` it goes back to the title on its own, the last
+/// `GAME_OVER_COUNTDOWN` seconds are counted down on screen.
+#[derive(Default)]
+struct GameOverState {
+  /// Cleared by any key or mouse button, the screen then waits for a retry.
+  remaining: Option<f32>,
+  countdown: Option<Entity>,
+  /// "New Best!" above the score of a run which took the top of its leaderboard.
+  banner: Option<Entity>,
+  /// Set with `GameConfig::auto_share_card` until the card of a new best is saved.
+  share_pending: bool,
+  /// Confirms that the share code was copied.
+  copied: Option<Entity>,
+}
+
+impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for GameOverState {
+  fn on_start(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    let world = data.world;
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(MENU_FONT, TtfFormat, (), &world.read_resource());
+    if world
+      .try_fetch::<FinishedRun>()
+      .map_or(false, |run| run.new_best)
+    {
+      let banner = world
+        .create_entity()
+        .with(UiTransform::new(
+          "new_best_banner".to_string(),
+          Anchor::Middle,
+          Anchor::Middle,
+          0.,
+          380.,
+          1.,
+          800.,
+          80.,
+        ))
+        .with(UiText::new(
+          font.clone(),
+          "New Best!".to_string(),
+          [1., 0.85, 0.2, 1.],
+          56.,
+          LineMode::Single,
+          Anchor::Middle,
+        ))
+        .build();
+      self.banner = Some(banner);
+      self.share_pending = world.read_resource::<GameConfig>().auto_share_card;
+    }
+    self.remaining = world.read_resource::<GameConfig>().game_over_timeout;
+    if self.remaining.is_none() {
+      return;
+    }
+
+    let countdown = world
+      .create_entity()
+      .with(UiTransform::new(
+        "game_over_countdown".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.,
+        30.,
+        1.,
+        800.,
+        40.,
+      ))
+      .with(UiText::new(
+        font,
+        String::new(),
+        [1., 1., 1., 0.6],
+        28.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .build();
+    self.countdown = Some(countdown);
+  }
+
+  fn on_stop(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
+    if let Some(countdown) = self.countdown.take() {
+      data
+        .world
+        .delete_entity(countdown)
+        .expect("Couldn't delete the game over countdown!");
+    }
+    if let Some(banner) = self.banner.take() {
+      data
+        .world
+        .delete_entity(banner)
+        .expect("Couldn't delete the new best banner!");
+    }
+    if let Some(copied) = self.copied.take() {
+      data
+        .world
+        .delete_entity(copied)
+        .expect("Couldn't delete the copied notice!");
+    }
+  }
+
+  fn handle_event(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+    event: MyStateEvent<StringBindings>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent<StringBindings>> {
+    if let MyStateEvent::Window(event) = &event {
+      if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
+        return Trans::Quit;
+      }
+      // In one button mode it's a retry just like every other key.
+      let one_button_mode = data.world.read_resource::<Settings>().one_button_mode;
+      if is_key_down(&event, VirtualKeyCode::F12) && !one_button_mode {
+        if let Some(run) = data.world.try_fetch::<FinishedRun>() {
+          save_share_card(run.clone());
+        }
+      }
+      if is_key_down(&event, VirtualKeyCode::C) && !one_button_mode {
+        self.copy_share_code(data.world);
+      }
+    }
+    if let MyStateEvent::Input(InputEvent::KeyPressed { .. })
+    | MyStateEvent::Input(InputEvent::MouseButtonPressed(_)) = &event
+    {
+      if self.remaining.take().is_some() {
+        let mut ui_text = data.world.write_storage::<UiText>();
+        if let Some(text) = self
+          .countdown
+          .and_then(|countdown| ui_text.get_mut(countdown))
+        {
+          text.text.clear();
+        }
+      }
+    }
+    if is_primary_action(
+      &event,
+      data.world.read_resource::<Settings>().one_button_mode,
+    ) {
+      Trans::Pop
+    } else {
+      Trans::None
+    }
+  }
+
+  fn update(
+    &mut self,
+    data: StateData<'_, GameData<'a, 'b>>,
+  ) -> Trans<GameData<'a, 'b>, MyStateEvent<StringBindings>> {
+    data.data.update(&data.world);
+    // The banner went up with the first frame of the screen, the card carries it as well.
+    if self.share_pending {
+      self.share_pending = false;
+      if let Some(run) = data.world.try_fetch::<FinishedRun>() {
+        save_share_card(run.clone());
+      }
+    }
+    if let Some(remaining) = self.remaining.as_mut() {
+      *remaining -= data.world.read_resource::<Time>().delta_seconds();
+      if *remaining <= 0. {
+        return Trans::Sequence(vec![Trans::Pop, Trans::Pop]);
+      }
+      if *remaining <= GAME_OVER_COUNTDOWN {
+        let mut ui_text = data.world.write_storage::<UiText>();
+        if let Some(text) = self
+          .countdown
+          .and_then(|countdown| ui_text.get_mut(countdown))
+        {
+          text.text = format!("Back to the title in {}", remaining.ceil());
+        }
+      }
+    }
+    Trans::None
+  }
+}
+
+impl GameOverState {
+  /// Copies the `share_code` of the run, `V` on the title screen plays it again.
+  fn copy_share_code(&mut self, world: &mut World) {
+    let code = match world.try_fetch::<FinishedRun>() {
+      Some(run) => share_code(run.result.seed, run.result.score),
+      None => return,
+    };
+    if let Err(e) = with_clipboard(|clipboard| clipboard.set_contents(code.clone())) {
+      warn!("Couldn't copy {} to the clipboard: {}", code, e);
+      return;
+    }
+    if self.copied.is_some() {
+      return;
+    }
+    let font =
+      world
+        .read_resource::<Loader>()
+        .load(MENU_FONT, TtfFormat, (), &world.read_resource());
+    let copied = world
+      .create_entity()
+      .with(UiTransform::new(
+        "share_code_copied".to_string(),
+        Anchor::BottomMiddle,
+        Anchor::BottomMiddle,
+        0.,
+        80.,
+        1.,
+        800.,
+        40.,
+      ))
+      .with(UiText::new(
+        font,
+        format!("Copied {}", code),
+        [1., 1., 1., 0.8],
+        28.,
+        LineMode::Single,
+        Anchor::Middle,
+      ))
+      .build();
+    self.copied = Some(copied);
+  }
+}
+
+/// The primary action starts a run on the title screen, flaps while playing and retries on the
+/// game over screen. Space always triggers it, in one button mode every key except Escape and
+/// every mouse button does as well.
+fn is_primary_action(event: &MyStateEvent, one_button_mode: bool) -> bool {
+  match event {
+    MyStateEvent::Input(InputEvent::KeyPressed { key_code, .. }) => {
+      *key_code == VirtualKeyCode::Space || (one_button_mode && *key_code != VirtualKeyCode::Escape)
+    }
+    MyStateEvent::Input(InputEvent::MouseButtonPressed(_)) => one_button_mode,
+    _ => false,
+  }
+}
+
+/// Same as `is_primary_action` but for systems polling the `InputHandler`.
+fn is_primary_action_down(input: &InputHandler<StringBindings>, one_button_mode: bool) -> bool {
+  input.key_is_down(VirtualKeyCode::Space)
+    || (one_button_mode
+      && (input
+        .keys_that_are_down()
+        .any(|key| key != VirtualKeyCode::Escape)
+        || input.mouse_buttons_that_are_down().next().is_some()))
+}
+
+/// A looping texture which is centered at `looping_offset` and then scrolled by up to
+/// `looping_point` has to cover the whole screen at both ends, otherwise the loop shows a seam.
+fn covers_screen_while_looping(
+  texture_width: f32,
+  looping_point: f32,
+  looping_offset: f32,
+) -> bool {
+  looping_offset - texture_width / 2. <= VIRTUAL_WIDTH / -2.
+    && looping_offset - looping_point + texture_width / 2. >= VIRTUAL_WIDTH / 2.
+}
+
+fn is_focus_lost(event: &Event) -> bool {
+  matches!(
+    event,
+    Event::WindowEvent {
+      event: WindowEvent::Focused(false),
+      ..
+    }
+  )
+}
+
+fn is_focus_gained(event: &Event) -> bool {
+  matches!(
+    event,
+    Event::WindowEvent {
+      event: WindowEvent::Focused(true),
+      ..
+    }
+  )
+}
+
+fn on_off(value: bool) -> &'static str {
+  if value {
+    "On"
+  } else {
+    "Off"
+  }
+}
+
+/// The multiple of `interval` a score of `score` reached, if it's above the last `reached` one.
+/// Points can come in bigger steps than one, skipped milestones only count as the highest. An
+/// `interval` of 0 has no milestones.
+fn milestone_crossed(reached: i32, score: i32, interval: u32) -> Option<i32> {
+  if interval == 0 {
+    return None;
+  }
+  let interval = interval as i32;
+  let milestone = score / interval * interval;
+  if milestone > reached && milestone > 0 {
+    Some(milestone)
+  } else {
+    None
+  }
+}
+
+fn point_in_rect(x: f32, y: f32, left: f32, bottom: f32, right: f32, top: f32) -> bool {
+  x >= left && x <= right && y >= bottom && y <= top
+}
+
+fn draw_outline(
+  lines: &mut DebugLines,
+  color: Srgba,
+  left: f32,
+  bottom: f32,
+  right: f32,
+  top: f32,
+) {
+  let corners = [
+    Point3::new(left, bottom, Z_PARTICLE),
+    Point3::new(right, bottom, Z_PARTICLE),
+    Point3::new(right, top, Z_PARTICLE),
+    Point3::new(left, top, Z_PARTICLE),
+  ];
+  for i in 0..corners.len() {
+    lines.draw_line(corners[i], corners[(i + 1) % corners.len()], color);
+  }
+}
+
+/// Middle of the overlap between the bird's rectangle and the `(left, bottom, right, top)` one.
+fn overlap_center(bird_x: f32, bird_y: f32, rect: (f32, f32, f32, f32)) -> (f32, f32) {
+  let (left, bottom, right, top) = rect;
+  let x = ((bird_x - BIRD_WIDTH / 2.).max(left) + (bird_x + BIRD_WIDTH / 2.).min(right)) / 2.;
+  let y = ((bird_y - BIRD_HEIGHT / 2.).max(bottom) + (bird_y + BIRD_HEIGHT / 2.).min(top)) / 2.;
+  (x, y)
+}
+
+/// Whether the circle touches the rectangle, its center may also be inside.
+fn circle_intersects_rect(
+  x: f32,
+  y: f32,
+  radius: f32,
+  left: f32,
+  bottom: f32,
+  right: f32,
+  top: f32,
+) -> bool {
+  let dx = x - x.max(left).min(right);
+  let dy = y - y.max(bottom).min(top);
+  dx * dx + dy * dy <= radius * radius
+}
+
+/// Pixels the camera shakes when a bird crashes with `dy`, only a fall shakes it.
+fn shake_magnitude(dy: f32, scale: f32, max: f32) -> f32 {
+  ((-dy).max(0.) * scale).min(max)
+}
+
+/// Tests the bird centered at `x`, `y` against an obstacle with the shape picked by
+/// `GameConfig::bird_collision_radius`.
+fn bird_hits_rect(
+  config: &GameConfig,
+  x: f32,
+  y: f32,
+  left: f32,
+  bottom: f32,
+  right: f32,
+  top: f32,
+) -> bool {
+  match config.bird_collision_radius {
+    Some(radius) => circle_intersects_rect(x, y, radius, left, bottom, right, top),
+    None => point_in_rect(
+      x,
+      y,
+      left - BIRD_WIDTH / 2.,
+      bottom - BIRD_HEIGHT / 2.,
+      right + BIRD_WIDTH / 2.,
+      top + BIRD_HEIGHT / 2.,
+    ),
+  }
+}
+
+fn init_camera(world: &mut World) {
+  let zoom = world.read_resource::<Settings>().camera_zoom;
+  let spatial_audio = world.read_resource::<GameConfig>().spatial_audio;
+  let mut camera = world
+    .create_entity()
+    .with(zoomed_camera(zoom))
+    .with(Transform::from(Vector3::new(0., 0., Z_CAMERA)));
+  if spatial_audio {
+    // Ears at the edges of the screen pan a sound fully once it's there.
+    camera = camera.with(AudioListener {
+      left_ear: Point3::new(VIRTUAL_WIDTH / -2., 0., 0.),
+      right_ear: Point3::new(VIRTUAL_WIDTH / 2., 0., 0.),
+    });
+  }
+  camera.build();
+}
+
+/// Turns the camera to `angle` radians, the world rotates the other way on screen.
+fn set_camera_rotation(world: &mut World, angle: f32) {
+  let cameras = world.read_storage::<Camera>();
+  let mut transforms = world.write_storage::<Transform>();
+  for (_, transform) in (&cameras, &mut transforms).join() {
+    transform.set_rotation_2d(angle);
+  }
+}
+
+/// The rotated camera shows what lies above and below the world, these solid sprites in the clear
+/// color hide the pipe ends there. They're sized for the camera zoomed out all the way.
+fn spawn_tube_masks(world: &mut World) -> Vec<Entity> {
+  let sprite = world.read_resource::<ParticleSprite>().sprite.clone();
+  let (r, g, b, a) = world.read_resource::<GameConfig>().clear_color;
+  let width = VIRTUAL_WIDTH * 2. / MIN_CAMERA_ZOOM;
+  let height = VIRTUAL_WIDTH / MIN_CAMERA_ZOOM;
+  [1., -1.]
+    .iter()
+    .map(|side: &f32| {
+      let mut transform = Transform::from(Vector3::new(
+        0.,
+        side * (VIRTUAL_HEIGHT + height) / 2.,
+        Z_TUBE_MASK,
+      ));
+      // The particle sprite is 4 by 4 pixels.
+      transform.set_scale(Vector3::new(width / 4., height / 4., 1.));
+      world
+        .create_entity()
+        .with(sprite.clone())
+        .with(transform)
+        .with(Tint(Srgba::new(r, g, b, a)))
+        .build()
+    })
+    .collect()
+}
+
+/// Falls back to random gaps if the level can't be loaded or has none. Gaps which can't be reached
+/// at the shortest spawn interval are only reported, the level is played as written.
+fn load_gap_source(world: &World) -> GapSource {
+  let config = world.read_resource::<GameConfig>();
+  let file = match &config.level {
+    Some(file) => file,
+    None => return GapSource::Random,
+  };
+  let level = match application_root_dir()
+    .map_err(|e| e.to_string())
+    .and_then(|root| Level::load(root.join("assets").join(file)).map_err(|e| e.to_string()))
+  {
+    Ok(level) => level,
+    Err(e) => {
+      error!(
+        "Couldn't load level {}, gaps are random instead: {}",
+        file, e
+      );
+      return GapSource::Random;
+    }
+  };
+  if level.gaps.is_empty() {
+    warn!("Level {} has no gaps, they're random instead.", file);
+    return GapSource::Random;
+  }
+
+  // Pairs of a wave follow each other closest, otherwise the spawn timer waits at least 2 seconds.
+  let dx = if config.pipe_wave_count > 1 {
+    config.pipe_wave_spacing
+  } else {
+    -PIPE_SCROLL * 2.
+  };
+  let mut pairs: Vec<(usize, usize)> = (1..level.gaps.len()).map(|i| (i - 1, i)).collect();
+  if level.when_done == LevelEnd::Loop {
+    pairs.push((level.gaps.len() - 1, 0));
+  }
+  for (from, to) in pairs {
+    // Checked with the tightest gaps the config may pick.
+    let (min_y, max_y) = reachable_gap_range(
+      level.gaps[from],
+      config.pipe_gap_min,
+      dx,
+      -PIPE_SCROLL,
+      config.pipe_gap_min,
+    );
+    if level.gaps[to] < min_y || level.gaps[to] > max_y {
+      warn!(
+        "Gap {} of level {} at {} can't be reached from {}.",
+        to, file, level.gaps[to], level.gaps[from]
+      );
+    }
+  }
+  GapSource::Scripted(level)
+}
+
+/// Only the visible area is scaled, the world coordinates used by spawning and collision stay
+/// untouched.
+fn zoomed_camera(zoom: f32) -> Camera {
+  Camera::standard_2d(VIRTUAL_WIDTH / zoom, VIRTUAL_HEIGHT / zoom)
+}
+
+fn load_settings() -> Settings {
+  application_root_dir()
+    .ok()
+    .and_then(|root| Settings::load(root.join(SETTINGS_PATH)).ok())
+    .unwrap_or_default()
+}
+
+fn save_settings(settings: &Settings) {
+  match application_root_dir() {
+    Ok(root) => {
+      if let Err(e) = settings.write(root.join(SETTINGS_PATH)) {
+        warn!("Couldn't save settings: {}", e);
+      }
+    }
+    Err(e) => warn!("Couldn't find application root to save settings: {}", e),
+  }
+}
+
+/// Every profile keeps its records in a file of its own.
+fn records_path(profile: usize) -> String {
+  format!("config/records_{}.ron", profile + 1)
+}
+
+fn load_records(profile: usize) -> Records {
+  application_root_dir()
+    .ok()
+    .and_then(|root| Records::load(root.join(records_path(profile))).ok())
+    .unwrap_or_default()
+}
+
+fn save_records(profile: usize, records: &Records) {
+  match application_root_dir() {
+    Ok(root) => {
+      if let Err(e) = records.write(root.join(records_path(profile))) {
+        warn!("Couldn't save records: {}", e);
+      }
+    }
+    Err(e) => warn!("Couldn't find application root to save records: {}", e),
+  }
+}
+
+/// A new run never starts with the flash of the last death still fading.
+fn clear_flash(world: &World) {
+  if let Some(mut flash) = world.try_fetch_mut::<Flash>() {
+    flash.alpha = 0.;
+    let mut images = world.write_storage::<UiImage>();
+    if let Some(image) = images.get_mut(flash.overlay) {
+      *image = UiImage::SolidColor([0., 0., 0., 0.]);
+    }
+  }
+}
+
+/// The bird is completely off the top of the screen.
+fn above_ceiling(bird_y: f32) -> bool {
+  bird_y - BIRD_WIDTH / 2. > VIRTUAL_HEIGHT / 2.
+}
+
+/// Gap height of the next pair, somewhere between `gap_min` and `gap_max` and then narrowed by the
+/// hurry up `shrink`. It never drops below `MIN_PIPE_GAP`, a fixed gap leaves the random sequence
+/// of the seed as it was.
+fn pick_gap(rand: &mut StdRng, gap_min: f32, gap_max: f32, shrink: f32) -> f32 {
+  let gap = if gap_max > gap_min {
+    rand.gen_range(gap_min, gap_max)
+  } else {
+    gap_min
+  };
+  (gap * shrink).max(MIN_PIPE_GAP)
+}
+
+/// Range of gap centers the bird can still get to from a gap of `from_gap` centered at `from_y`,
+/// when the next pair with a gap of `gap` is `dx` away and the pipes scroll with `speed` pixels
+/// per second. Climbing is limited to flapping at every apex, a drop to free fall from a
+/// standstill.
+fn reachable_gap_range(from_y: f32, from_gap: f32, dx: f32, speed: f32, gap: f32) -> (f32, f32) {
+  let time = ((dx.abs() - PIPE_WIDTH) / speed).max(0.);
+  // The bird may leave a gap at its top and enter the next one at its bottom, or the other way.
+  let slack = ((from_gap + gap) / 2. - BIRD_HEIGHT).max(0.);
+  let climb = BIRD_JUMP / (2. * PHYSICS_STEP) * time;
+  let drop = 0.5 * (-BIRD_GRAVITY / PHYSICS_STEP) * time * time;
+  (from_y - slack - drop, from_y + slack + climb)
+}
+
+/// Moves the gap center `y` of the next pair into `reachable_gap_range`.
+fn clamp_to_reachable(y: f32, from_y: f32, from_gap: f32, dx: f32, speed: f32, gap: f32) -> f32 {
+  let (min_y, max_y) = reachable_gap_range(from_y, from_gap, dx, speed, gap);
+  y.max(min_y).min(max_y)
+}
+
+/// Gap center of every pipe pair by its x, positions are compared as if the world always scrolled
+/// to the left.
+fn gap_centers(
+  pipes: &ReadStorage<'_, Pipe>,
+  transforms: &WriteStorage<'_, Transform>,
+  sign: f32,
+) -> Vec<(f32, f32)> {
+  let mut lower_tops = Vec::new();
+  let mut upper_bottoms = Vec::new();
+  for (_, transform) in (pipes, transforms).join() {
+    let x = sign * transform.translation().x;
+    let y = transform.translation().y;
+    if y < 0. {
+      lower_tops.push((x, y + PIPE_HEIGHT / 2.));
+    } else {
+      upper_bottoms.push((x, y - PIPE_HEIGHT / 2.));
+    }
+  }
+  lower_tops
+    .into_iter()
+    .filter_map(|(x, top)| {
+      upper_bottoms
+        .iter()
+        .find(|(upper_x, _)| (upper_x - x).abs() < 1.)
+        .map(|(_, bottom)| (x, (top + bottom) / 2.))
+    })
+    .collect()
+}
+
+/// Flaps once the bird is falling and will soon sink below `target_y`, a weaker assist lets it sink
+/// further towards the lower pipe first. Heights are compared as if gravity always pulled down.
+fn assist_wants_flap(y: f32, dy: f32, target_y: f32, gravity_sign: f32, strength: f32) -> bool {
+  let falling = gravity_sign * dy < 0.;
+  let projected = gravity_sign * (y + dy * ASSIST_LOOKAHEAD - target_y);
+  falling && projected < -(1. - strength) * PIPE_GAP / 2.
+}
+
+/// `random_y` is the center of the gap, relative to the center of the screen. Returns the lower
+/// and the upper pipe.
+fn spawn_pipe_pair(
+  world: &mut World,
+  sprite: SpriteRender,
+  x: f32,
+  random_y: f32,
+  gap: f32,
+) -> (Entity, Entity) {
+  let lower = world
+    .create_entity()
+    .with(Pipe::default())
+    .with(sprite.clone())
+    .with(Transform::from(Vector3::new(
+      x,
+      -VIRTUAL_HEIGHT / 2. + random_y - gap / 2.,
+      Z_PIPE,
+    )))
+    .build();
+  let upper = world
+    .create_entity()
+    .with(Pipe::default())
+    .with(sprite)
+    .with({
+      let mut transform = Transform::from(Vector3::new(
+        x,
+        VIRTUAL_HEIGHT / 2. + random_y + gap / 2.,
+        Z_PIPE,
+      ));
+      transform.set_rotation_2d(std::f32::consts::PI);
+      transform
+    })
+    .build();
+  (lower, upper)
+}
+
+fn reset_start_grace(world: &mut World) {
+  let remaining = world.read_resource::<GameConfig>().start_grace_period;
+  world.insert(StartGrace { remaining });
+}
+
+fn primary_action_held(world: &World) -> bool {
+  is_primary_action_down(
+    &world.read_resource::<InputHandler<StringBindings>>(),
+    world.read_resource::<Settings>().one_button_mode,
+  )
+}
+
+/// Hides or shows the "title" and "sub_title" texts and returns the title, if the UI defines it.
+/// A missing text is only warned about, so an edited UI file can't crash the game.
+fn set_title_hidden(world: &mut World, hide: bool) -> Option<Entity> {
+  let mut e_title = None;
+  let mut e_sub_title = None;
+  world.exec(|finder: UiFinder| {
+    e_title = finder.find("title");
+    e_sub_title = finder.find("sub_title");
+  });
+
+  let mut hidden = world.write_storage::<Hidden>();
+  for (name, entity) in &[("title", e_title), ("sub_title", e_sub_title)] {
+    match entity {
+      Some(entity) if hide => {
+        if let Err(err) = hidden.insert(*entity, Hidden) {
+          warn!("Couldn't hide {}: {}", name, err);
+        }
+      }
+      // Already being visible is fine.
+      Some(entity) => {
+        hidden.remove(*entity);
+      }
+      None => warn!("The UI has no \"{}\" text, skipping it.", name),
+    }
+  }
+  e_title
+}
+
+fn spawn_bird(world: &mut World, sprite: SpriteRender) {
+  let dy = world.read_resource::<GameConfig>().start_dy;
+  // A key still held from starting the run mustn't flap right away or once it's let go.
+  let held_on_entry = primary_action_held(world);
+  let score = world.read_resource::<GameMode>().start_points();
+  let mut transform = Transform::from(Vector3::new(0., 0., Z_BIRD));
+  let facing = world.read_resource::<GameConfig>().scroll_direction.sign();
+  transform.set_scale(Vector3::new(facing, 1., 1.));
+  let tint = world.read_resource::<Settings>().bird_tint();
+  let spatial_audio = world.read_resource::<GameConfig>().spatial_audio;
+  let mut bird = world
+    .create_entity()
+    .with(Bird {
+      dy,
+      score,
+      held_on_entry,
+      ..Default::default()
+    })
+    .with(sprite)
+    .with(transform)
+    .with(tint);
+  if spatial_audio {
+    bird = bird.with(AudioEmitter::default());
+  }
+  bird.build();
+}
+
+/// Unix time as a `YYYY-MM-DD` date in UTC.
+fn format_date(secs: u64) -> String {
+  // Days to a civil date, counted in 400 year eras starting on the 1st of March.
+  let days = (secs / 86400) as i64 + 719_468;
+  let era = days / 146_097;
+  let day_of_era = days - era * 146_097;
+  let year_of_era =
+    (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let month_index = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+  let month = if month_index < 10 {
+    month_index + 3
+  } else {
+    month_index - 9
+  };
+  let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+  format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `flippy:<seed in hex>:<score>`, copied on the game over screen so others can fly the same pipes.
+fn share_code(seed: u64, score: i32) -> String {
+  format!("flippy:{:x}:{}", seed, score)
+}
+
+/// Seed and score of a `share_code`, whitespace around it is ignored.
+fn parse_share_code(code: &str) -> Option<(u64, i32)> {
+  let mut parts = code.trim().splitn(3, ':');
+  if parts.next()? != "flippy" {
+    return None;
+  }
+  let seed = u64::from_str_radix(parts.next()?, 16).ok()?;
+  let score = parts.next()?.parse().ok()?;
+  Some((seed, score))
+}
+
+thread_local! {
+  /// On X11 the copied text is only handed out while the context which copied it is around.
+  static CLIPBOARD: RefCell<Option<ClipboardContext>> = RefCell::new(None);
+}
+
+/// Runs `f` with the clipboard of the system, it's opened on first use.
+fn with_clipboard<T>(
+  f: impl FnOnce(&mut ClipboardContext) -> Result<T, Box<dyn std::error::Error>>,
+) -> Result<T, Box<dyn std::error::Error>> {
+  CLIPBOARD.with(|clipboard| {
+    let mut clipboard = clipboard.borrow_mut();
+    let context = match clipboard.take() {
+      Some(context) => context,
+      None => ClipboardProvider::new()?,
+    };
+    f(clipboard.get_or_insert(context))
+  })
+}
+
+/// Where a share card taken at the Unix time `secs` is saved below the assets, the time in UTC.
+fn share_card_path(secs: u64, score: i32) -> String {
+  format!(
+    "{}/{}_{:02}{:02}{:02}_score_{}.png",
+    SCREENSHOT_DIR,
+    format_date(secs),
+    secs / 3600 % 24,
+    secs / 60 % 60,
+    secs % 60,
+    score
+  )
+}
+
+/// Renders the share card of `run` and saves it, on a thread of its own so the game over screen
+/// keeps drawing while the PNG is encoded.
+fn save_share_card(run: FinishedRun) {
+  let assets = match application_root_dir() {
+    Ok(root) => root.join("assets"),
+    Err(e) => {
+      warn!(
+        "Couldn't find application root to save the share card: {}",
+        e
+      );
+      return;
+    }
+  };
+  let secs = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or(0);
+  let path = assets.join(share_card_path(secs, run.result.score));
+  std::thread::spawn(move || match write_share_card(&assets, &path, &run) {
+    Ok(()) => info!("Saved the share card to {}", path.display()),
+    Err(e) => warn!("Couldn't save the share card to {}: {}", path.display(), e),
+  });
+}
+
+fn write_share_card(
+  assets: &Path,
+  path: &Path,
+  run: &FinishedRun,
+) -> Result<(), Box<dyn std::error::Error>> {
+  let font = Font::try_from_vec(std::fs::read(assets.join(MENU_FONT))?)
+    .ok_or("the menu font isn't a font")?;
+  let background = image::open(assets.join("texture/background.png"))?.to_rgba();
+  let logo = image::open(assets.join("texture/logo.png"))?.to_rgba();
+  let card = share_card(run, &background, &logo, &font);
+  if let Some(dir) = path.parent() {
+    create_dir_all(dir)?;
+  }
+  card.save(path)?;
+  Ok(())
+}
+
+/// The logo and the stats of `run` on the start of the background, drawn like the screen at
+/// `SHARE_CARD_SCALE` times its size.
+fn share_card(
+  run: &FinishedRun,
+  background: &RgbaImage,
+  logo: &RgbaImage,
+  font: &Font<'_>,
+) -> RgbaImage {
+  let width = VIRTUAL_WIDTH as u32 * SHARE_CARD_SCALE;
+  let height = VIRTUAL_HEIGHT as u32 * SHARE_CARD_SCALE;
+  let view = imageops::crop_imm(
+    background,
+    0,
+    0,
+    background.width().min(VIRTUAL_WIDTH as u32),
+    background.height().min(VIRTUAL_HEIGHT as u32),
+  )
+  .to_image();
+  let mut card = imageops::resize(&view, width, height, FilterType::Nearest);
+  let logo = imageops::resize(
+    logo,
+    logo.width() * 3 * SHARE_CARD_SCALE / 2,
+    logo.height() * 3 * SHARE_CARD_SCALE / 2,
+    FilterType::Nearest,
+  );
+  imageops::overlay(&mut card, &logo, width.saturating_sub(logo.width()) / 2, 40);
+
+  // A dark band keeps the texts readable in front of the bright sky.
+  let band_top = height / 2 - 90;
+  for y in band_top..(band_top + 230).min(height) {
+    for x in 0..width {
+      card.get_pixel_mut(x, y).blend(&Rgba([0, 0, 0, 140]));
+    }
+  }
+  if run.new_best {
+    draw_centered_text(
+      &mut card,
+      font,
+      "New Best!",
+      40.,
+      band_top - 50,
+      [255, 217, 51],
+    );
+  }
+  let headline = if run.result.completed {
+    "Level Complete!".to_string()
+  } else {
+    format!("Score: {}", run.result.score)
+  };
+  let stats = format!(
+    "{}  -  {:.1}s  -  {} Coins",
+    run.mode.label(),
+    run.result.duration,
+    run.result.coins
+  );
+  draw_centered_text(
+    &mut card,
+    font,
+    &headline,
+    64.,
+    band_top + 20,
+    [255, 255, 255],
+  );
+  draw_centered_text(
+    &mut card,
+    font,
+    &stats,
+    32.,
+    band_top + 110,
+    [255, 255, 255],
+  );
+  draw_centered_text(
+    &mut card,
+    font,
+    &share_code(run.result.seed, run.result.score),
+    24.,
+    band_top + 170,
+    [200, 200, 200],
+  );
+  card
+}
+
+/// Draws `text` centered across `image`, its line starting `top` pixels from the top.
+fn draw_centered_text(
+  image: &mut RgbaImage,
+  font: &Font<'_>,
+  text: &str,
+  size: f32,
+  top: u32,
+  [r, g, b]: [u8; 3],
+) {
+  let scale = Scale::uniform(size);
+  let ascent = font.v_metrics(scale).ascent;
+  let glyphs: Vec<_> = font
+    .layout(text, scale, point(0., top as f32 + ascent))
+    .collect();
+  let text_width = glyphs.last().map_or(0., |glyph| {
+    glyph.position().x + glyph.unpositioned().h_metrics().advance_width
+  });
+  let offset = ((image.width() as f32 - text_width) / 2.).round() as i32;
+  for glyph in &glyphs {
+    let bounds = match glyph.pixel_bounding_box() {
+      Some(bounds) => bounds,
+      None => continue,
+    };
+    glyph.draw(|x, y, coverage| {
+      let x = offset + bounds.min.x + x as i32;
+      let y = bounds.min.y + y as i32;
+      if x >= 0 && y >= 0 && (x as u32) < image.width() && (y as u32) < image.height() {
+        let alpha = (coverage * 255.).round() as u8;
+        image
+          .get_pixel_mut(x as u32, y as u32)
+          .blend(&Rgba([r, g, b, alpha]));
+      }
+    });
+  }
+}
+
+fn format_score(score: i32, format: ScoreFormat) -> String {
+  let sign = if score < 0 { "-" } else { "" };
+  let digits = (score as i64).abs().to_string();
+  match format {
+    ScoreFormat::Plain => score.to_string(),
+    ScoreFormat::Separated => {
+      let mut text = sign.to_string();
+      for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+          text.push(',');
         }
+        text.push(c);
+      }
+      text
+    }
+    ScoreFormat::Suffix => {
+      let n = (score as i64).abs() as f64;
+      let (value, suffix) = if n >= 1_000_000. {
+        (n / 1_000_000., "M")
+      } else if n >= 1_000. {
+        (n / 1_000., "k")
+      } else {
+        return score.to_string();
+      };
+      // Cut off instead of rounding, so 999999 doesn't turn into 1000.0k.
+      let value = format!("{:.1}", (value * 10.).floor() / 10.);
+      format!("{}{}{}", sign, value.trim_end_matches(".0"), suffix)
+    }
+  }
+}
+
+/// Adds the `points` of a pass to `score` and returns it with its HUD text. It saturates instead
+/// of overflowing and stops at `max_score`, the text is marked with " MAX" once it's reached.
+fn add_score(
+  score: i32,
+  points: i32,
+  max_score: Option<i32>,
+  format: ScoreFormat,
+) -> (i32, String) {
+  let mut score = score.saturating_add(points);
+  let maxed_out = max_score.map_or(false, |max| score >= max);
+  if let Some(max) = max_score {
+    score = score.min(max);
+  }
+  let mut text = format_score(score.max(0), format);
+  if maxed_out {
+    text.push_str(" MAX");
+  }
+  (score, text)
+}
+
+/// Hides the texts `GameConfig::hud` turns off, or every one of them with `Settings::hide_hud`.
+fn apply_hud(world: &World) {
+  let layout = world.read_resource::<GameConfig>().hud;
+  let hide_all = world.read_resource::<Settings>().hide_hud;
+  let (combo, combo_meter) = {
+    let combo = world.read_resource::<Combo>();
+    (combo.text, combo.meter)
+  };
+  let elements = [
+    (world.read_resource::<Score>().text, layout.score),
+    (combo, layout.combo),
+    (combo_meter, layout.combo),
+    (world.read_resource::<Coins>().text, layout.coins),
+    (world.read_resource::<Rewind>().text, layout.coins),
+  ];
+  let mut hidden = world.write_storage::<Hidden>();
+  for (e, element) in elements.iter() {
+    if hide_all || !element.visible {
+      hidden
+        .insert(*e, Hidden)
+        .expect("Error while trying to hide a HUD text!");
+    } else {
+      hidden.remove(*e);
+    }
+  }
+}
+
+/// Puts a `TextShadow` behind each of the `sources` unless `GameConfig::text_shadow` is `None`.
+fn spawn_text_shadows(world: &mut World, sources: &[Entity]) {
+  if world.read_resource::<GameConfig>().text_shadow.is_none() {
+    return;
+  }
+  for source in sources {
+    let shadow = {
+      let texts = world.read_storage::<UiText>();
+      let transforms = world.read_storage::<UiTransform>();
+      match (texts.get(*source), transforms.get(*source)) {
+        (Some(text), Some(transform)) => Some((
+          UiTransform::new(
+            format!("{}_shadow", transform.id),
+            transform.anchor,
+            transform.pivot,
+            transform.local_x,
+            transform.local_y,
+            transform.local_z - 0.5,
+            transform.width,
+            transform.height,
+          ),
+          UiText::new(
+            text.font.clone(),
+            text.text.clone(),
+            [0., 0., 0., 0.],
+            text.font_size,
+            text.line_mode,
+            text.align,
+          ),
+        )),
+        _ => None,
       }
+    };
+    if let Some((transform, text)) = shadow {
+      world
+        .create_entity()
+        .with(transform)
+        .with(text)
+        .with(TextShadow { source: *source })
+        .build();
+    }
+  }
+}
+
+/// Removes what a run has spawned and resets its counters, the score is left alone.
+fn clear_run(world: &World) {
+  delete_run_entities(world);
+  // A retry with the noclip still on doesn't count either.
+  *world.write_resource::<RunStats>() = RunStats {
+    noclip_used: world.read_resource::<NoClip>().active,
+    ..RunStats::default()
+  };
+  world.write_resource::<GravityFlip>().remaining = 0.;
+  let mut combo = world.write_resource::<Combo>();
+  combo.count = 0;
+  combo.break_flash = 0.;
+  combo.remaining = 0.;
+  let mut ui_text = world.write_storage::<UiText>();
+  if let Some(text) = ui_text.get_mut(combo.text) {
+    text.text.clear();
+  }
+  let mut rewind = world.write_resource::<Rewind>();
+  rewind.charges = 0;
+  if let Some(text) = ui_text.get_mut(rewind.text) {
+    text.text.clear();
+  }
+  world
+    .write_storage::<Hidden>()
+    .insert(world.read_resource::<HurryUpCue>().text, Hidden)
+    .expect("Error while trying to hide hurry up cue!");
+  let mut milestone = world.write_resource::<Milestone>();
+  milestone.reached = 0;
+  milestone.remaining = 0.;
+  world
+    .write_storage::<Hidden>()
+    .insert(milestone.text, Hidden)
+    .expect("Error while trying to hide the milestone banner!");
+}
+
+/// Removes everything a run has spawned, the UI is left alone.
+fn delete_run_entities(world: &World) {
+  let entities = world.entities();
+  let pipes = world.read_storage::<Pipe>();
+  for (e, _) in (&entities, &pipes).join() {
+    entities
+      .delete(e)
+      .expect("Couldn't delete pipe entity while state was paused!");
+  }
+  let birds = world.read_storage::<Bird>();
+  for (e, _) in (&entities, &birds).join() {
+    entities
+      .delete(e)
+      .expect("Couldn't delete bird entity while state was paused!");
+  }
+  let particles = world.read_storage::<Particle>();
+  for (e, _) in (&entities, &particles).join() {
+    entities
+      .delete(e)
+      .expect("Couldn't delete particle entity while state was paused!");
+  }
+  let power_ups = world.read_storage::<PowerUp>();
+  for (e, _) in (&entities, &power_ups).join() {
+    entities
+      .delete(e)
+      .expect("Couldn't delete power-up entity while state was paused!");
+  }
+  let coins = world.read_storage::<Coin>();
+  for (e, _) in (&entities, &coins).join() {
+    entities
+      .delete(e)
+      .expect("Couldn't delete coin entity while state was paused!");
+  }
+  let wells = world.read_storage::<GravityWell>();
+  for (e, _) in (&entities, &wells).join() {
+    entities
+      .delete(e)
+      .expect("Couldn't delete gravity well entity while state was paused!");
+  }
+  if let Some(mut guide) = world.try_fetch_mut::<RhythmGuide>() {
+    if let Some(ring) = guide.ring.take() {
+      entities
+        .delete(ring)
+        .expect("Couldn't delete rhythm ring while state was paused!");
+    }
+    guide.phase = 0.;
+  }
+}
+
+/// Shortest translation which moves the point out of the rect through one of its edges.
+fn separation_vector(x: f32, y: f32, left: f32, bottom: f32, right: f32, top: f32) -> Vector3<f32> {
+  let candidates = [
+    Vector3::new(left - x, 0., 0.),
+    Vector3::new(right - x, 0., 0.),
+    Vector3::new(0., bottom - y, 0.),
+    Vector3::new(0., top - y, 0.),
+  ];
+  candidates
+    .iter()
+    .copied()
+    .min_by(|a, b| {
+      a.norm()
+        .partial_cmp(&b.norm())
+        .unwrap_or(std::cmp::Ordering::Equal)
+    })
+    .unwrap_or_else(Vector3::zeros)
+}
+
+fn read_ui_layout() -> Result<ron::Value, Box<dyn std::error::Error>> {
+  let path = application_root_dir()?.join("assets").join(UI_LAYOUT);
+  let layout = std::fs::read_to_string(path)?;
+  Ok(ron::de::from_str(&layout)?)
+}
+
+fn asset_exists(path: &str) -> bool {
+  application_root_dir()
+    .map(|root| root.join("assets").join(path).is_file())
+    .unwrap_or(false)
+}
+
+fn set_score_font(world: &World, str: &str) -> String {
+  let score = world.read_resource::<Score>();
+  let mut ui_text = world.write_storage::<UiText>();
+  if let Some(text) = ui_text.get_mut(score.text) {
+    let last_score = text.text.clone();
+    text.text = str.to_string();
+    return last_score;
+  }
+  return "0".to_string();
+}
+
+fn load_digit_sprites(world: &World) -> SpriteRender {
+  load_sprite("texture/digits.png", "texture/digits.ron", 0, world)
+}
+
+fn load_sprite<T>(image: T, ron: T, number: usize, world: &World) -> SpriteRender
+where
+  T: Into<String>,
+{
+  let ron = ron.into();
+  let texture_handle = {
+    let loader = world.read_resource::<Loader>();
+    let texture_storage = world.read_resource::<AssetStorage<Texture>>();
+    let filter = world.read_resource::<GameConfig>().texture_filter;
+    let format = ImageFormat(ImageTextureConfig {
+      sampler_info: SamplerInfo::new(filter.into(), WrapMode::Clamp),
+      ..Default::default()
+    });
+    loader.load(image, format, (), &texture_storage)
+  };
+
+  let sprite_handle = {
+    let loader = world.read_resource::<Loader>();
+    let sprite_sheet_store = world.read_resource::<AssetStorage<SpriteSheet>>();
+    if ron.ends_with(".json") {
+      loader.load(
+        ron,
+        JsonSpriteSheetFormat(texture_handle),
+        (),
+        &sprite_sheet_store,
+      )
+    } else {
+      loader.load(
+        ron,
+        SpriteSheetFormat(texture_handle),
+        (),
+        &sprite_sheet_store,
+      )
+    }
+  };
+
+  SpriteRender::new(sprite_handle, number)
+}
+
+fn load_audio_track_wav(loader: &Loader, world: &World, file: &str) -> SourceHandle {
+  loader.load(file, WavFormat, (), &world.read_resource())
+}
+
+fn load_music_track(
+  loader: &Loader,
+  world: &World,
+  file: &str,
+  progress: impl Progress,
+) -> SourceHandle {
+  match Path::new(file).extension().and_then(|ext| ext.to_str()) {
+    Some("ogg") => loader.load(file, OggFormat, progress, &world.read_resource()),
+    Some("wav") => loader.load(file, WavFormat, progress, &world.read_resource()),
+    _ => loader.load(file, Mp3Format, progress, &world.read_resource()),
+  }
+}
+
+/// What the DJ plays next. Without any tracks, or once every one of them failed to load, there's
+/// nothing to cycle through and the game runs silently.
+fn next_track(music: &mut Music) -> Option<SourceHandle> {
+  if music.loading.num_failed() >= music.tracks.len() {
+    return None;
+  }
+  music.music.next()
+}
+
+/// Sorted by file name unless `shuffle` is set, a missing folder is the same as an empty one.
+fn music_tracks(shuffle: bool) -> Vec<String> {
+  let dir = match application_root_dir() {
+    Ok(root) => root.join("assets").join(MUSIC_DIR),
+    Err(e) => {
+      error!("Failed to find the music folder: {}", e);
+      return Vec::new();
+    }
+  };
+  let mut tracks = match read_dir(&dir) {
+    Ok(entries) => entries
+      .filter_map(|entry| entry.ok())
+      .map(|entry| entry.path())
+      .filter(|path| path.is_file())
+      .filter(|path| {
+        matches!(
+          path.extension().and_then(|ext| ext.to_str()),
+          Some("mp3") | Some("ogg") | Some("wav")
+        )
+      })
+      .filter_map(|path| {
+        path
+          .file_name()
+          .and_then(|name| name.to_str())
+          .map(|name| format!("{}/{}", MUSIC_DIR, name))
+      })
+      .collect::<Vec<_>>(),
+    Err(e) => {
+      warn!("Failed to read {}: {}", dir.display(), e);
+      Vec::new()
+    }
+  };
+  tracks.sort();
+  if shuffle {
+    tracks.shuffle(&mut thread_rng());
+  }
+  for track in &tracks {
+    info!("Found music track {}", track);
+  }
+  tracks
+}
+
+/// Master volume of the operating system.
+trait SystemVolume {
+  /// From `0` for muted to `1` for full, `None` where it can't be read.
+  fn level(&self) -> Option<f32>;
+}
+
+/// Leaves the music at its usual volume.
+struct NoSystemVolume;
+
+impl SystemVolume for NoSystemVolume {
+  fn level(&self) -> Option<f32> {
+    None
+  }
+}
+
+/// Asks PulseAudio, or PipeWire in its place, for the volume of the default output.
+struct PactlVolume;
+
+impl SystemVolume for PactlVolume {
+  fn level(&self) -> Option<f32> {
+    let output = std::process::Command::new("pactl")
+      .args(&["get-sink-volume", "@DEFAULT_SINK@"])
+      .output()
+      .ok()?;
+    if !output.status.success() {
+      return None;
+    }
+    parse_pactl_volume(&String::from_utf8_lossy(&output.stdout))
+  }
+}
+
+/// Average of the channels in the output of `pactl get-sink-volume`, e.g.
+/// `Volume: front-left: 32768 /  50% / -18.06 dB,   front-right: 32768 /  50% / -18.06 dB`.
+fn parse_pactl_volume(output: &str) -> Option<f32> {
+  let levels: Vec<f32> = output
+    .split('/')
+    .filter_map(|part| part.trim().strip_suffix('%'))
+    .filter_map(|percent| percent.trim().parse::<f32>().ok())
+    .collect();
+  if levels.is_empty() {
+    return None;
+  }
+  Some(levels.iter().sum::<f32>() / levels.len() as f32 / 100.)
+}
+
+/// The master volume query of this platform with `GameConfig::adaptive_volume`, only Linux has one.
+fn system_volume(adaptive_volume: bool) -> Box<dyn SystemVolume> {
+  if adaptive_volume && cfg!(target_os = "linux") {
+    Box::new(PactlVolume)
+  } else {
+    Box::new(NoSystemVolume)
+  }
+}
+
+/// Volume of the music sink, a quiet system turns it down further and a boosted one above 100%
+/// doesn't turn it up.
+fn music_volume(system: &dyn SystemVolume) -> f32 {
+  MUSIC_VOLUME * system.level().map_or(1., |level| level.max(0.).min(1.))
+}
 
-      for (background, transform) in (&backgrounds, &transforms).join() {
-        match background.b_type {
-          BackgroundType::Background => {}
-          BackgroundType::Ground => {
-            let background_x = transform.translation().y - (GROUND_WIDTH / 2.);
-            let background_y = transform.translation().y - (GROUND_HEIGHT / 2.);
+fn init_audio(world: &mut World) {
+  let mut jump_sounds = world.read_resource::<GameConfig>().jump_sounds.clone();
+  let tracks = music_tracks(world.read_resource::<GameConfig>().shuffle_music);
+  jump_sounds.retain(|file| {
+    let exists = asset_exists(file);
+    if !exists {
+      warn!("Jump sound {} is missing, it's left out.", file);
+    }
+    exists
+  });
+  if jump_sounds.is_empty() {
+    warn!("No jump sounds are configured, using {}.", JUMP_SOUND);
+    jump_sounds.push(JUMP_SOUND.to_string());
+  }
+  let (sound_effects, music) = {
+    let loader = world.read_resource::<Loader>();
 
-            if point_in_rect(
-              bird_x,
-              bird_y,
-              background_x - BIRD_WIDTH / 2.,
-              background_y - BIRD_HEIGHT / 2.,
-              background_x + GROUND_WIDTH + BIRD_WIDTH / 2.,
-              background_y + GROUND_HEIGHT + BIRD_HEIGHT / 2.,
-            ) {
-              event_ch.single_write(GameEvent::Collision);
-              play_hurt_sound(&*sounds, &storage, output.as_deref());
-            }
-          }
+    let adaptive_volume = world.read_resource::<GameConfig>().adaptive_volume;
+    let mut sink = world.write_resource::<AudioSink>();
+    sink.set_volume(music_volume(system_volume(adaptive_volume).as_ref()));
+
+    if tracks.is_empty() {
+      warn!(
+        "No music tracks in {}, the game runs without music.",
+        MUSIC_DIR
+      );
+    }
+    let mut loading = ProgressCounter::new();
+    let music = tracks
+      .iter()
+      .map(|file| load_music_track(&loader, &world, file, &mut loading))
+      .collect::<Vec<_>>()
+      .into_iter()
+      .cycle();
+    let music = Music {
+      music,
+      tracks,
+      loading,
+    };
+
+    let sound = Sounds {
+      score_sfx: load_audio_track_wav(&loader, &world, SCORE_SOUND),
+      hurt_sfx: load_audio_track_wav(&loader, &world, HURT_SOUND),
+      explosion_sfx: load_audio_track_wav(&loader, &world, EXPLOSION_SOUND),
+      jump_sfx: jump_sounds
+        .iter()
+        .map(|file| load_audio_track_wav(&loader, &world, file))
+        .collect(),
+      whoosh_sfx: load_audio_track_wav(&loader, &world, WHOOSH_SOUND),
+      tick_sfx: load_audio_track_wav(&loader, &world, TICK_SOUND),
+      combo_lost_sfx: load_audio_track_wav(&loader, &world, COMBO_LOST_SOUND),
+      coin_sfx: load_audio_track_wav(&loader, &world, COIN_SOUND),
+      ground_hit_sfx: load_audio_track_wav(&loader, &world, GROUND_HIT_SOUND),
+      pipe_hit_sfx: load_audio_track_wav(&loader, &world, PIPE_HIT_SOUND),
+      ceiling_hit_sfx: load_audio_track_wav(&loader, &world, CEILING_HIT_SOUND),
+      milestone_sfx: load_audio_track_wav(&loader, &world, MILESTONE_SOUND),
+    };
+
+    (sound, music)
+  };
+
+  world.insert(sound_effects);
+  world.insert(music);
+}
+
+/// Every record is written with a single append, so lines never interleave.
+fn append_run_log(path: &str, result: &RunResult) -> std::io::Result<()> {
+  let path = application_root_dir()?.join(path);
+  if let Some(dir) = path.parent() {
+    create_dir_all(dir)?;
+  }
+  let timestamp = SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or(0);
+  let mut line = serde_json::to_string(&RunRecord { timestamp, result })?;
+  line.push('\n');
+  OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)?
+    .write_all(line.as_bytes())
+}
+
+fn play_score_sound(
+  sounds: &Sounds,
+  emitter: Option<&mut AudioEmitter>,
+  storage: &AssetStorage<Source>,
+  output: Option<&Output>,
+) {
+  play_bird_sound(&sounds.score_sfx, 0.25, emitter, storage, output);
+}
+
+fn play_sound(
+  handle: &SourceHandle,
+  volume: f32,
+  storage: &AssetStorage<Source>,
+  output: Option<&Output>,
+) {
+  if let Some(ref output) = output.as_ref() {
+    if let Some(sound) = storage.get(handle) {
+      output.play_once(sound, volume);
+    }
+  }
+}
+
+/// Goes through the emitter of the bird if it has one, see `GameConfig::spatial_audio`. Emitters
+/// always play at full volume, so `volume` only applies to flat sounds.
+fn play_bird_sound(
+  handle: &SourceHandle,
+  volume: f32,
+  emitter: Option<&mut AudioEmitter>,
+  storage: &AssetStorage<Source>,
+  output: Option<&Output>,
+) {
+  match emitter {
+    Some(emitter) => {
+      if let Some(sound) = storage.get(handle) {
+        if let Err(e) = emitter.play(sound) {
+          warn!("Couldn't play a positioned sound: {}", e);
         }
       }
     }
+    None => play_sound(handle, volume, storage, output),
   }
 }
 
-struct ScoreSystem;
-
-impl<'a> System<'a> for ScoreSystem {
-  type SystemData = (
-    WriteStorage<'a, Bird>,
-    WriteStorage<'a, Pipe>,
-    ReadStorage<'a, Transform>,
-    WriteStorage<'a, UiText>,
-    ReadExpect<'a, Score>,
-    Read<'a, AssetStorage<Source>>,
-    ReadExpect<'a, Sounds>,
-    Option<Read<'a, Output>>,
+/// The hurt sound always plays, `distinct` swaps the explosion for the sound of the `cause`.
+fn play_hurt_sound(
+  sounds: &Sounds,
+  cause: CollisionCause,
+  distinct: bool,
+  mut emitter: Option<&mut AudioEmitter>,
+  storage: &AssetStorage<Source>,
+  output: Option<&Output>,
+) {
+  play_bird_sound(
+    &sounds.hurt_sfx,
+    0.25,
+    emitter.as_deref_mut(),
+    storage,
+    output,
   );
+  let impact = match cause {
+    _ if !distinct => &sounds.explosion_sfx,
+    CollisionCause::Ground => &sounds.ground_hit_sfx,
+    CollisionCause::Pipe => &sounds.pipe_hit_sfx,
+    CollisionCause::Ceiling => &sounds.ceiling_hit_sfx,
+  };
+  play_bird_sound(impact, 0.25, emitter, storage, output);
+}
 
-  fn run(
-    &mut self,
-    (mut birds, mut pipes, transforms, mut ui_text, score, storage, sounds, output): Self::SystemData,
-  ) {
-    for (bird, transform) in (&mut birds, &transforms).join() {
-      let bird_x = transform.translation().x;
+fn play_hurry_up_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
+  if let Some(ref output) = output.as_ref() {
+    if let Some(sound) = storage.get(&sounds.score_sfx) {
+      output.play_once(sound, 0.5);
+    }
+  }
+}
 
-      for (pipe, transform) in (&mut pipes, &transforms).join() {
-        let pipe_x = transform.translation().x + (PIPE_WIDTH / 2.);
-        let pipe_y = transform.translation().y + (PIPE_HEIGHT / 2.);
+fn play_whoosh_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
+  if let Some(ref output) = output.as_ref() {
+    if let Some(sound) = storage.get(&sounds.whoosh_sfx) {
+      output.play_once(sound, 0.2);
+    }
+  }
+}
 
-        if !pipe.is_scored && pipe_x < bird_x && pipe_y < 0. {
-          pipe.is_scored = true;
-          bird.score += 1;
+fn play_combo_lost_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
+  play_sound(&sounds.combo_lost_sfx, 0.25, storage, output);
+}
 
-          play_score_sound(&*sounds, &storage, output.as_deref());
+fn play_coin_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
+  play_sound(&sounds.coin_sfx, 0.25, storage, output);
+}
 
-          if let Some(text) = ui_text.get_mut(score.text) {
-            text.text = bird.score.to_string();
-          }
-        }
-      }
-    }
+fn play_tick_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
+  play_sound(&sounds.tick_sfx, 0.2, storage, output);
+}
+
+fn play_jump_sound(
+  sounds: &Sounds,
+  emitter: Option<&mut AudioEmitter>,
+  storage: &AssetStorage<Source>,
+  output: Option<&Output>,
+) {
+  if let Some(handle) = sounds.jump_sfx.choose(&mut thread_rng()) {
+    play_bird_sound(handle, 0.15, emitter, storage, output);
   }
 }
 
-#[derive(Default)]
-struct TitleScreenState;
+fn main() -> amethyst::Result<()> {
+  amethyst::start_logger(Default::default());
 
-impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for TitleScreenState {
-  fn on_start(&mut self, _data: StateData<'_, GameData<'_, '_>>) {
-    let world = _data.world;
+  let app_root = application_root_dir()?;
+  let display_conf_path = app_root.join("config/display.ron");
+  let game_conf_path = app_root.join("config/game.ron");
+  let assets_dir = app_root.join("assets");
+  let game_config = GameConfig::load(game_conf_path)?.validated();
+  let (r, g, b, a) = game_config.clear_color;
+  let mut display_config = DisplayConfig::load(display_conf_path)?;
+  // Smaller windows squash the UI into an unusable state, `min_dimensions` can lower this.
+  if display_config.min_dimensions.is_none() {
+    display_config.min_dimensions = Some((VIRTUAL_WIDTH as u32, VIRTUAL_HEIGHT as u32));
+  }
 
-    init_camera(world);
-    init_audio(world);
+  // The right trigger of the first gamepad flaps, it only reports anything when built with the
+  // `gamepad` feature.
+  let mut bindings = Bindings::<StringBindings>::new();
+  bindings.insert_axis(
+    FLAP_AXIS,
+    Axis::Controller {
+      controller_id: 0,
+      axis: ControllerAxis::RightTrigger,
+      invert: false,
+      dead_zone: 0.,
+    },
+  )?;
 
-    let background_sprite =
-      load_sprite("texture/background.png", "texture/background.ron", 0, world);
-    let ground_sprite = load_sprite("texture/ground.png", "texture/ground.ron", 0, world);
+  let game_data = GameDataBuilder::default()
+    .with_system_desc(DjSystemDesc::new(next_track), "dj_system", &[])
+    .with(BackgroundSystem, "background_system", &[])
+    .with(CameraZoomSystem::default(), "camera_zoom_system", &[])
+    .with(TimeScaleSystem::default(), "time_scale_system", &[])
+    .with(RunLogSystem::default(), "run_log_system", &[])
+    .with(DebugOverlaySystem::default(), "debug_overlay_system", &[])
+    .with(ImpactMarkerSystem, "impact_marker_system", &[])
+    .with(
+      ProfilerOverlaySystem::default(),
+      "profiler_overlay_system",
+      &[],
+    )
+    .with(UiScaleSystem, "ui_scale_system", &[])
+    .with(UiReloadSystem::default(), "ui_reload_system", &[])
+    .with(HideUiSystem::default(), "hide_ui_system", &[])
+    .with(TitleBobSystem, "title_bob_system", &[])
+    .with(ScoreDigitsSystem, "score_digits_system", &[])
+    .with(
+      TextShadowSystem,
+      "text_shadow_system",
+      &["ui_scale_system", "hide_ui_system"],
+    )
+    .with(FlashSystem, "flash_system", &[])
+    .with(ShakeSystem, "shake_system", &[])
+    .with_bundle(FpsCounterBundle::default())?
+    .with_bundle(TransformBundle::new())?
+    .with_bundle(InputBundle::<StringBindings>::new().with_bindings(bindings))?
+    .with_bundle(UiBundle::<StringBindings>::new())?
+    .with_bundle(AudioBundle::default())?
+    .with_bundle(
+      RenderingBundle::<DefaultBackend>::new()
+        .with_plugin(RenderToWindow::from_config(display_config).with_clear([r, g, b, a]))
+        .with_plugin(RenderUi::default())
+        .with_plugin(RenderFlat2D::default())
+        .with_plugin(RenderDebugLines::default()),
+    )?;
+  #[cfg(feature = "gamepad")]
+  let game_data = game_data.with_thread_local_desc(amethyst::input::SdlEventsSystemDesc::<
+    StringBindings,
+  >::new(None));
+  let mut settings = load_settings();
+  if settings.profile >= PROFILE_COUNT {
+    warn!(
+      "Profile {} doesn't exist, using the first one.",
+      settings.profile + 1
+    );
+    settings.profile = 0;
+  }
+  let records = load_records(settings.profile);
+  let (strategy, fps) = settings.frame_cap.limit();
+  let mut game = CoreApplication::<_, MyStateEvent, MyStateEventReader>::build(
+    assets_dir,
+    SplashState {
+      screenshot: std::env::args().any(|arg| arg == "--screenshot"),
+      ..Default::default()
+    },
+  )?
+  .with_frame_limit(strategy, fps)
+  .with_resource(game_config)
+  .with_resource(settings)
+  .with_resource(records)
+  .build(game_data)?;
+  game.run();
+  Ok(())
+}
 
-    world.exec(|mut creator: UiCreator<'_>| {
-      creator.create("ui/text.ron", ());
-    });
+#[cfg(test)]
+mod tests {
+  use super::*;
 
-    world
+  fn assert_close(actual: f32, expected: f32) {
+    assert!(
+      (actual - expected).abs() <= 1e-4 * expected.abs().max(1.),
+      "expected {}, got {}",
+      expected,
+      actual
+    );
+  }
+
+  /// A world with a fixed-step clock and nothing but a bird at the origin, no assets loaded.
+  fn bird_world(config: GameConfig) -> (World, Entity) {
+    let mut world = World::new();
+    System::setup(&mut BirdSystem, &mut world);
+    world.insert(PhysicsClock::default());
+    world.insert(config);
+    let text = world.create_entity().build();
+    world.insert(GravityFlip {
+      remaining: 0.,
+      text,
+    });
+    let bird = world
       .create_entity()
-      .with(Background {
-        b_type: BackgroundType::Background,
-        scroll_pos: 0.,
-      })
-      .with(background_sprite)
-      .with(Transform::from(Vector3::new(
-        BACKGROUND_LOOPING_OFFSET,
-        0.,
-        0.,
-      )))
+      .with(Bird::default())
+      .with(Transform::default())
       .build();
+    (world, bird)
+  }
+
+  /// Runs the physics steps of a frame which took `delta` seconds, like `PlayState` does.
+  fn run_frame(world: &mut World, delta: f32) {
+    let steps = world.write_resource::<PhysicsClock>().advance(delta);
+    for _ in 0..steps {
+      BirdSystem.run_now(world);
+    }
+    world.maintain();
+  }
+
+  fn bird_dy(world: &World, bird: Entity) -> f32 {
+    world.read_storage::<Bird>().get(bird).unwrap().dy
+  }
 
+  fn bird_y(world: &World, bird: Entity) -> f32 {
     world
-      .create_entity()
-      .with(Background {
-        b_type: BackgroundType::Ground,
-        scroll_pos: 0.,
-      })
-      .with(ground_sprite)
-      .with(Transform::from(Vector3::new(
-        BACKGROUND_LOOPING_OFFSET,
-        (VIRTUAL_HEIGHT - GROUND_HEIGHT) / -2.,
-        2.,
-      )))
-      .build();
+      .read_storage::<Transform>()
+      .get(bird)
+      .unwrap()
+      .translation()
+      .y
   }
 
-  fn on_pause(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
-    let world = data.world;
+  #[test]
+  fn gravity_lowers_dy_every_step() {
+    let (mut world, bird) = bird_world(GameConfig::default());
+    run_frame(&mut world, PHYSICS_STEP);
+    assert_close(bird_dy(&world, bird), BIRD_GRAVITY * PHYSICS_STEP);
+    assert_close(bird_y(&world, bird), BIRD_GRAVITY * PHYSICS_STEP);
+
+    run_frame(&mut world, PHYSICS_STEP);
+    assert_close(bird_dy(&world, bird), 2. * BIRD_GRAVITY * PHYSICS_STEP);
+    assert_close(bird_y(&world, bird), 3. * BIRD_GRAVITY * PHYSICS_STEP);
+  }
 
-    let mut e_title = None;
-    let mut e_sub_title = None;
-    world.exec(|finder: UiFinder| {
-      e_title = finder.find("title");
-      e_sub_title = finder.find("sub_title");
+  #[test]
+  fn flap_adds_bird_jump() {
+    let (mut world, bird) = bird_world(GameConfig {
+      jump_mode: JumpMode::Add,
+      flap_trigger: FlapTrigger::Release,
+      ..Default::default()
     });
+    // Nothing is held in the test world, so a key pressed in the last step was just let go.
+    world
+      .write_storage::<Bird>()
+      .get_mut(bird)
+      .unwrap()
+      .fly_pressed = true;
+    run_frame(&mut world, PHYSICS_STEP);
+    assert_close(
+      bird_dy(&world, bird),
+      BIRD_GRAVITY * PHYSICS_STEP + BIRD_JUMP,
+    );
+    assert_close(
+      bird_y(&world, bird),
+      BIRD_GRAVITY * PHYSICS_STEP + BIRD_JUMP,
+    );
+  }
 
-    let mut hidden = world.write_storage::<Hidden>();
+  #[test]
+  fn format_score_separated() {
+    let format = |score| format_score(score, ScoreFormat::Separated);
+    assert_eq!(format(0), "0");
+    assert_eq!(format(999), "999");
+    assert_eq!(format(1000), "1,000");
+    assert_eq!(format(999_999), "999,999");
+    assert_eq!(format(1_000_000), "1,000,000");
+    assert_eq!(format(-1_234_567), "-1,234,567");
+    assert_eq!(format(-999), "-999");
+  }
 
-    if let Some(entity) = e_title {
-      hidden
-        .insert(entity, Hidden)
-        .expect("Error while trying to hide title!");
-    }
+  #[test]
+  fn format_score_suffix() {
+    let format = |score| format_score(score, ScoreFormat::Suffix);
+    assert_eq!(format(999), "999");
+    assert_eq!(format(1000), "1k");
+    assert_eq!(format(1050), "1k");
+    assert_eq!(format(1250), "1.2k");
+    // Cut off, not rounded up to the next unit.
+    assert_eq!(format(999_999), "999.9k");
+    assert_eq!(format(1_000_000), "1M");
+    assert_eq!(format(2_560_000), "2.5M");
+    assert_eq!(format(-999), "-999");
+    assert_eq!(format(-1500), "-1.5k");
+    assert_eq!(format(-1_000_000), "-1M");
+  }
 
-    if let Some(entity) = e_sub_title {
-      hidden
-        .insert(entity, Hidden)
-        .expect("Error while trying to hide sub_title!");
+  #[test]
+  fn circle_touching_an_edge_intersects() {
+    assert!(circle_intersects_rect(15., 5., 5., 0., 0., 10., 10.));
+    assert!(circle_intersects_rect(5., -5., 5., 0., 0., 10., 10.));
+    assert!(!circle_intersects_rect(15.1, 5., 5., 0., 0., 10., 10.));
+  }
+
+  #[test]
+  fn circle_near_a_corner_misses() {
+    // Within the radius of both edges, but further than it from the corner itself.
+    assert!(!circle_intersects_rect(14., 14., 5., 0., 0., 10., 10.));
+    assert!(!circle_intersects_rect(-4., -4., 5., 0., 0., 10., 10.));
+    assert!(circle_intersects_rect(13., 13., 5., 0., 0., 10., 10.));
+  }
+
+  #[test]
+  fn circle_inside_the_rect_intersects() {
+    assert!(circle_intersects_rect(5., 5., 1., 0., 0., 10., 10.));
+    assert!(circle_intersects_rect(5., 5., 20., 0., 0., 10., 10.));
+  }
+
+  /// Whether a rectangle shaped bird at `y`, above the middle of the ground, hits it.
+  fn bird_hits_ground(config: &GameConfig, y: f32) -> bool {
+    let (left, bottom, right, top) = config.ground_hitbox(0., 0.);
+    bird_hits_rect(config, 0., y, left, bottom, right, top)
+  }
+
+  #[test]
+  fn ground_hit_at_the_boundary() {
+    let config = GameConfig::default();
+    let touching = GROUND_HEIGHT / 2. + BIRD_HEIGHT / 2.;
+    assert!(bird_hits_ground(&config, touching));
+    assert!(!bird_hits_ground(&config, touching + 1.));
+  }
+
+  #[test]
+  fn ground_hit_honours_the_tolerance() {
+    let config = GameConfig {
+      ground_collision_tolerance: 4.,
+      ..Default::default()
+    };
+    let touching = GROUND_HEIGHT / 2. + BIRD_HEIGHT / 2.;
+    assert!(!bird_hits_ground(&config, touching));
+    assert!(!bird_hits_ground(&config, touching - 3.));
+    assert!(bird_hits_ground(&config, touching - 4.));
+    assert!(bird_hits_ground(&config, touching - 5.));
+  }
+
+  /// Whether a rectangle shaped bird at `x`, `y` hits a pipe centered at the origin.
+  fn bird_hits_pipe(config: &GameConfig, x: f32, y: f32) -> bool {
+    let (left, bottom, right, top) = config.pipe_hitbox(0., 0.);
+    bird_hits_rect(config, x, y, left, bottom, right, top)
+  }
+
+  #[test]
+  fn pipe_hit_at_the_boundary() {
+    let config = GameConfig::default();
+    let side = PIPE_WIDTH / 2. + BIRD_WIDTH / 2.;
+    let end = PIPE_HEIGHT / 2. + BIRD_HEIGHT / 2.;
+    // Each side with the way out of the pipe.
+    for &(x, y, out_x, out_y) in &[
+      (-side, 0., -1., 0.),
+      (side, 0., 1., 0.),
+      (0., -end, 0., -1.),
+      (0., end, 0., 1.),
+    ] {
+      assert!(bird_hits_pipe(&config, x, y));
+      assert!(!bird_hits_pipe(&config, x + out_x, y + out_y));
     }
   }
 
-  fn handle_event(
-    &mut self,
-    _data: StateData<'_, GameData<'_, '_>>,
-    event: MyStateEvent,
-  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
-    if let MyStateEvent::Window(event) = &event {
-      if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
-        return Trans::Quit;
-      }
-      if is_key_down(&event, VirtualKeyCode::Space) {
-        return Trans::Push(Box::new(PlayState::default()));
-      }
+  #[test]
+  fn pipe_hit_uses_the_collision_width() {
+    let margin = 6.;
+    let config = GameConfig {
+      pipe_collision_width: PIPE_WIDTH - 2. * margin,
+      ..Default::default()
+    };
+    let side = PIPE_WIDTH / 2. + BIRD_WIDTH / 2.;
+    for &sign in &[-1., 1.] {
+      assert!(!bird_hits_pipe(&config, sign * side, 0.));
+      assert!(!bird_hits_pipe(&config, sign * (side - margin + 1.), 0.));
+      assert!(bird_hits_pipe(&config, sign * (side - margin), 0.));
+    }
+
+    let config = GameConfig {
+      pipe_collision_width: PIPE_WIDTH + 2. * margin,
+      ..Default::default()
+    };
+    for &sign in &[-1., 1.] {
+      assert!(bird_hits_pipe(&config, sign * (side + margin), 0.));
+      assert!(!bird_hits_pipe(&config, sign * (side + margin + 1.), 0.));
     }
-    Trans::None
   }
 
-  fn update(
-    &mut self,
-    data: StateData<'_, GameData<'a, 'b>>,
-  ) -> Trans<GameData<'a, 'b>, MyStateEvent<StringBindings>> {
-    data.data.update(&data.world);
-    Trans::None
+  #[test]
+  fn milestone_crossing() {
+    assert_eq!(milestone_crossed(0, 9, 10), None);
+    assert_eq!(milestone_crossed(0, 10, 10), Some(10));
+    assert_eq!(milestone_crossed(0, 25, 0), None);
+    // Two milestones crossed at once only celebrate the higher one.
+    assert_eq!(milestone_crossed(10, 31, 10), Some(30));
+    // A score sitting on the reached milestone doesn't fire it again.
+    assert_eq!(milestone_crossed(10, 10, 10), None);
+    assert_eq!(milestone_crossed(10, 15, 10), None);
+    assert_eq!(milestone_crossed(0, -10, 10), None);
   }
-}
 
-#[derive(Default)]
-struct PlayState {
-  pipe_spawn_timer: Option<f32>,
-  pipe_sprite: Option<SpriteRender>,
-  bird_sprite: Option<SpriteRender>,
-  rand: Option<ThreadRng>,
-  dispatcher: Option<Dispatcher<'static, 'static>>,
-}
+  #[test]
+  fn pass_classification_thresholds() {
+    let config = GameConfig::default().validated();
+    assert_eq!(config.classify_pass(COMBO_MARGIN - 0.1), PassKind::Tight);
+    assert_eq!(config.classify_pass(COMBO_MARGIN), PassKind::Normal);
+    assert_eq!(config.classify_pass(WIDE_PASS_MARGIN), PassKind::Normal);
+    assert_eq!(config.classify_pass(WIDE_PASS_MARGIN + 0.1), PassKind::Wide);
 
-impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for PlayState {
-  fn on_start(&mut self, _data: StateData<'_, GameData<'_, '_>>) {
-    let world = _data.world;
+    // A wide margin below the near miss one is raised to it, only that exact margin is normal.
+    let config = GameConfig {
+      near_miss_margin: 20.,
+      wide_pass_margin: 10.,
+      ..Default::default()
+    }
+    .validated();
+    assert_eq!(config.classify_pass(19.9), PassKind::Tight);
+    assert_eq!(config.classify_pass(20.), PassKind::Normal);
+    assert_eq!(config.classify_pass(20.1), PassKind::Wide);
 
-    let mut dispatcher_builder = DispatcherBuilder::new();
-    dispatcher_builder.add(BirdSystem, "bird_system", &[]);
-    dispatcher_builder.add(PipeSystem, "pipe_system", &[]);
-    dispatcher_builder.add(
-      CollisionSystem,
-      "collision_system",
-      &["bird_system", "pipe_system"],
-    );
-    dispatcher_builder.add(ScoreSystem, "score_system", &["bird_system", "pipe_system"]);
-    let mut dispatcher = dispatcher_builder.build();
-    dispatcher.setup(world);
-    self.dispatcher = Some(dispatcher);
+    let config = GameConfig {
+      near_miss_margin: -5.,
+      ..Default::default()
+    }
+    .validated();
+    assert_eq!(config.classify_pass(0.), PassKind::Normal);
+  }
 
-    let pipe_sprite = load_sprite("texture/pipe.png", "texture/pipe.ron", 0, world);
-    let bird_sprite = load_sprite("texture/bird.png", "texture/bird.ron", 0, world);
-    self.pipe_spawn_timer.replace(2.);
-    self.pipe_sprite.replace(pipe_sprite);
-    self.bird_sprite.replace(bird_sprite.clone());
-    self.rand.replace(thread_rng());
+  #[test]
+  fn flap_impulse_scales_with_fall_speed() {
+    let flat = GameConfig::default().validated();
+    for &dy in &[-10., 0., 10.] {
+      assert_close(flat.flap_impulse(dy), BIRD_JUMP);
+    }
 
-    let font =
-      world
-        .read_resource::<Loader>()
-        .load("font/font.ttf", TtfFormat, (), &world.read_resource());
+    let scaled = GameConfig {
+      flap_fall_scaling: 0.5,
+      ..Default::default()
+    }
+    .validated();
+    assert_close(scaled.flap_impulse(-BIRD_JUMP), BIRD_JUMP * 1.5);
+    assert_close(scaled.flap_impulse(-BIRD_JUMP / 2.), BIRD_JUMP * 1.25);
+    assert_close(scaled.flap_impulse(0.), BIRD_JUMP);
+    assert_close(scaled.flap_impulse(BIRD_JUMP), BIRD_JUMP * 0.5);
+    assert_close(scaled.flap_impulse(-100.), BIRD_JUMP * 1.5);
 
-    let text = world
-      .create_entity()
-      .with(UiTransform::new(
-        "score".to_string(),
-        Anchor::TopMiddle,
-        Anchor::TopMiddle,
-        0.,
-        -50.,
-        1.,
-        400.,
-        200.,
-      ))
-      .with(UiText::new(
-        font,
-        "0".to_string(),
-        [1., 1., 1., 1.],
-        100.,
-        LineMode::Single,
-        Anchor::Middle,
-      ))
-      .build();
+    let extreme = GameConfig {
+      flap_fall_scaling: 2.,
+      ..Default::default()
+    }
+    .validated();
+    assert_close(extreme.flap_impulse(-BIRD_JUMP), BIRD_JUMP * JUMP_ADD_MAX);
+    assert_close(extreme.flap_impulse(BIRD_JUMP), BIRD_JUMP * FLAP_SCALE_MIN);
+
+    let negative = GameConfig {
+      flap_fall_scaling: -1.,
+      ..Default::default()
+    }
+    .validated();
+    assert_close(negative.flap_impulse(-BIRD_JUMP), BIRD_JUMP);
+  }
 
-    world.insert(Score { text });
+  #[test]
+  fn gravity_ramp() {
+    let config = GameConfig::default().validated();
+    assert_close(config.gravity_scale(0.), 1.);
 
-    world
-      .create_entity()
-      .with(Bird::default())
-      .with(bird_sprite)
-      .with(Transform::from(Vector3::new(0., 0., 4.)))
-      .build();
+    let config = GameConfig {
+      gravity_ramp_duration: 2.,
+      gravity_ramp_start: 0.5,
+      ..Default::default()
+    }
+    .validated();
+    assert_close(config.gravity_scale(0.), 0.5);
+    assert_close(config.gravity_scale(1.), 0.75);
+    assert_close(config.gravity_scale(2.), 1.);
+    assert_close(config.gravity_scale(5.), 1.);
+
+    let config = GameConfig {
+      gravity_ramp_duration: -1.,
+      ..Default::default()
+    }
+    .validated();
+    assert_close(config.gravity_scale(0.), 1.);
+
+    let config = GameConfig {
+      gravity_ramp_duration: 2.,
+      gravity_ramp_start: -0.5,
+      ..Default::default()
+    }
+    .validated();
+    assert_close(config.gravity_scale(0.), 0.);
+    assert_close(config.gravity_scale(1.), 0.5);
   }
 
-  fn on_pause(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
-    let world = data.world;
-    {
-      let pipes = world.read_storage::<Pipe>();
-      let entities = world.entities();
-      for (e, _) in (&entities, &pipes).join() {
-        entities
-          .delete(e)
-          .expect("Couldn't delete pipe entity while state was paused!");
+  #[test]
+  fn pipes_despawn_only_out_of_sight() {
+    for &despawn_margin in &[0., PIPE_WIDTH / 2., -50.] {
+      let config = GameConfig {
+        despawn_margin,
+        ..Default::default()
       }
-      let birds = world.read_storage::<Bird>();
-      for (e, _) in (&entities, &birds).join() {
-        entities
-          .delete(e)
-          .expect("Couldn't delete bird entity while state was paused!");
+      .validated();
+      for &zoom in &[MIN_CAMERA_ZOOM, 1., 2.] {
+        let despawn_x = config.pipe_despawn_x(zoom);
+        let edge = VIRTUAL_WIDTH / zoom / -2.;
+        // A pipe still showing a pixel stays, one past the margin is removed.
+        let just_inside = edge - PIPE_WIDTH / 2. + 1.;
+        let past = edge - PIPE_WIDTH / 2. - config.despawn_margin - 1.;
+        assert!(just_inside >= despawn_x);
+        assert!(past < despawn_x);
       }
     }
+  }
 
-    let last_score = set_score_font(world, "");
+  #[test]
+  fn reachable_gap_range_follows_the_physics() {
+    let speed = -PIPE_SCROLL;
+    let slack = PIPE_GAP - BIRD_HEIGHT;
+    for &dx in &[PIPE_WIDTH, 150., 300.] {
+      let time = (dx - PIPE_WIDTH) / speed;
+      let climb = BIRD_JUMP / (2. * PHYSICS_STEP) * time;
+      let drop = 0.5 * (-BIRD_GRAVITY / PHYSICS_STEP) * time * time;
+      let (min_y, max_y) = reachable_gap_range(20., PIPE_GAP, dx, speed, PIPE_GAP);
+      assert_close(max_y, 20. + slack + climb);
+      assert_close(min_y, 20. - slack - drop);
 
-    let mut e_title = None;
-    let mut e_sub_title = None;
-    world.exec(|finder: UiFinder| {
-      e_title = finder.find("title");
-      e_sub_title = finder.find("sub_title");
-    });
+      // A mirrored scroll spawns the next pair on the other side, it's just as far away.
+      let mirrored = reachable_gap_range(20., PIPE_GAP, -dx, speed, PIPE_GAP);
+      assert_close(mirrored.0, min_y);
+      assert_close(mirrored.1, max_y);
+    }
 
-    let mut hidden = world.write_storage::<Hidden>();
+    // Overlapping pairs leave no time to fly, only the gaps themselves overlap.
+    let (min_y, max_y) = reachable_gap_range(0., PIPE_GAP, PIPE_WIDTH / 2., speed, PIPE_GAP);
+    assert_close(min_y, -slack);
+    assert_close(max_y, slack);
+  }
 
-    if let Some(entity) = e_title {
-      hidden
-        .remove(entity)
-        .expect("Error while trying to show title!");
-    }
+  fn assert_uv(sprite: &Sprite, left: f32, right: f32, top: f32, bottom: f32) {
+    assert_close(sprite.tex_coords.left, left);
+    assert_close(sprite.tex_coords.right, right);
+    assert_close(sprite.tex_coords.top, top);
+    assert_close(sprite.tex_coords.bottom, bottom);
+  }
 
-    if let Some(entity) = e_sub_title {
-      hidden
-        .remove(entity)
-        .expect("Error while trying to show sub_title!");
-    }
+  #[test]
+  fn json_atlas_array() {
+    let atlas = br#"{
+      "frames": [
+        { "filename": "a.png", "frame": { "x": 0, "y": 0, "w": 32, "h": 16 } },
+        { "filename": "b.png", "frame": { "x": 32, "y": 16, "w": 32, "h": 16 }, "rotated": false }
+      ],
+      "meta": { "size": { "w": 64, "h": 32 } }
+    }"#;
+    let sprites = JsonSpriteSheetFormat::sprites(atlas).unwrap();
+    assert_eq!(sprites.len(), 2);
+    assert_close(sprites[0].width, 32.);
+    assert_close(sprites[0].height, 16.);
+    assert_uv(&sprites[0], 0., 0.5, 0., 0.5);
+    assert_uv(&sprites[1], 0.5, 1., 0.5, 1.);
+  }
 
-    let mut ui_text = world.write_storage::<UiText>();
-    if let Some(final_score_display) = e_title.and_then(|entity| ui_text.get_mut(entity)) {
-      final_score_display.text = format!("Your Score: {}", last_score);
+  #[test]
+  fn json_atlas_hash() {
+    let atlas = br#"{
+      "frames": {
+        "b.png": { "frame": { "x": 16, "y": 0, "w": 16, "h": 32 } },
+        "a.png": { "frame": { "x": 0, "y": 0, "w": 16, "h": 32 } }
+      },
+      "meta": { "size": { "w": 32, "h": 32 } }
+    }"#;
+    let sprites = JsonSpriteSheetFormat::sprites(atlas).unwrap();
+    assert_eq!(sprites.len(), 2);
+    assert_uv(&sprites[0], 0., 0.5, 0., 1.);
+    assert_uv(&sprites[1], 0.5, 1., 0., 1.);
+  }
+
+  #[test]
+  fn json_atlas_rejects_rotated_frames() {
+    let atlas = br#"{
+      "frames": [{ "frame": { "x": 0, "y": 0, "w": 8, "h": 8 }, "rotated": true }],
+      "meta": { "size": { "w": 8, "h": 8 } }
+    }"#;
+    assert!(JsonSpriteSheetFormat::sprites(atlas).is_err());
+  }
+
+  #[test]
+  fn fastest_dive_cannot_tunnel_through_the_ground() {
+    let config = GameConfig::default();
+    // The ground is the thinnest obstacle, pipes are much taller than a single step.
+    let (left, bottom, right, top) =
+      config.ground_hitbox(0., (VIRTUAL_HEIGHT - GROUND_HEIGHT) / -2.);
+    // A fall from the top of the screen is the fastest the bird gets, the starts cover every
+    // position in between two steps.
+    for offset in 0..20 {
+      let (mut world, bird) = bird_world(GameConfig::default());
+      let start = VIRTUAL_HEIGHT / 2. + offset as f32;
+      world
+        .write_storage::<Transform>()
+        .get_mut(bird)
+        .unwrap()
+        .set_translation_y(start);
+      let mut hit = false;
+      while !hit && bird_y(&world, bird) > bottom - BIRD_HEIGHT / 2. {
+        run_frame(&mut world, PHYSICS_STEP);
+        hit = bird_hits_rect(&config, 0., bird_y(&world, bird), left, bottom, right, top);
+      }
+      assert!(hit, "a fall from {} went through the ground", start);
     }
   }
 
-  fn on_resume(&mut self, data: StateData<'_, GameData<'a, 'b>>) {
-    let world = data.world;
+  /// Flaps triggered by the key being down or up in consecutive steps.
+  fn flaps(bird: &mut Bird, trigger: FlapTrigger, steps: &[bool]) -> Vec<bool> {
+    steps
+      .iter()
+      .map(|down| bird.flap_triggered(*down, trigger))
+      .collect()
+  }
 
-    set_score_font(world, "0");
+  #[test]
+  fn press_trigger_flaps_when_the_key_goes_down() {
+    let steps = [false, true, true, false, true, false];
+    assert_eq!(
+      flaps(&mut Bird::default(), FlapTrigger::Press, &steps),
+      [false, true, false, false, true, false]
+    );
+  }
 
-    if let Some(sprite) = self.bird_sprite.clone() {
+  #[test]
+  fn release_trigger_flaps_when_the_key_is_let_go() {
+    let steps = [false, true, true, false, false, true, false];
+    assert_eq!(
+      flaps(&mut Bird::default(), FlapTrigger::Release, &steps),
+      [false, false, false, true, false, false, true]
+    );
+  }
+
+  #[test]
+  fn key_held_into_a_run_doesnt_flap() {
+    // Like `spawn_bird` with the key from the title screen still down.
+    let held = || Bird {
+      held_on_entry: true,
+      ..Default::default()
+    };
+    assert_eq!(
+      flaps(&mut held(), FlapTrigger::Press, &[true, true, false, true]),
+      [false, false, false, true]
+    );
+    assert_eq!(
+      flaps(
+        &mut held(),
+        FlapTrigger::Release,
+        &[true, true, false, true, false]
+      ),
+      [false, false, false, false, true]
+    );
+  }
+
+  #[test]
+  fn key_held_across_a_resume_doesnt_flap() {
+    for &trigger in &[FlapTrigger::Press, FlapTrigger::Release] {
+      let mut bird = Bird::default();
+      // Held down before the pause menu opened and still down when it's left.
+      flaps(&mut bird, trigger, &[true]);
+      bird.hold_until_released();
+      assert_eq!(flaps(&mut bird, trigger, &[true, false]), [false, false]);
+      let expected = match trigger {
+        FlapTrigger::Press => [true, false],
+        FlapTrigger::Release => [false, true],
+      };
+      assert_eq!(flaps(&mut bird, trigger, &[true, false]), expected);
+    }
+  }
+
+  fn ui_world(ids: &[&str]) -> World {
+    let mut world = World::new();
+    world.register::<UiTransform>();
+    world.register::<Hidden>();
+    for id in ids {
       world
         .create_entity()
-        .with(Bird::default())
-        .with(sprite)
-        .with(Transform::from(Vector3::new(0., 0., 4.)))
+        .with(UiTransform::new(
+          id.to_string(),
+          Anchor::Middle,
+          Anchor::Middle,
+          0.,
+          0.,
+          1.,
+          100.,
+          50.,
+        ))
         .build();
     }
+    world
+  }
 
-    let mut e_title = None;
-    let mut e_sub_title = None;
-    world.exec(|finder: UiFinder| {
-      e_title = finder.find("title");
-      e_sub_title = finder.find("sub_title");
-    });
+  #[test]
+  fn missing_titles_are_skipped() {
+    let mut world = ui_world(&["score"]);
+    assert_eq!(set_title_hidden(&mut world, true), None);
+    assert_eq!(set_title_hidden(&mut world, false), None);
+  }
 
-    let mut hidden = world.write_storage::<Hidden>();
+  #[test]
+  fn titles_can_be_shown_twice() {
+    let mut world = ui_world(&["title", "sub_title"]);
+    let title = set_title_hidden(&mut world, true).unwrap();
+    assert!(world.read_storage::<Hidden>().contains(title));
+    assert_eq!(set_title_hidden(&mut world, false), Some(title));
+    assert_eq!(set_title_hidden(&mut world, false), Some(title));
+    assert!(!world.read_storage::<Hidden>().contains(title));
+  }
 
-    if let Some(entity) = e_title {
-      hidden
-        .insert(entity, Hidden)
-        .expect("Error while trying to hide title!");
+  /// A bird moving at `dy` whose key was let go this step, so it flaps with
+  /// `FlapTrigger::Release` in a world where nothing is held.
+  fn flapping_bird_world(config: GameConfig, dy: f32) -> (World, Entity) {
+    let (world, bird) = bird_world(GameConfig {
+      flap_trigger: FlapTrigger::Release,
+      ..config
+    });
+    {
+      let mut birds = world.write_storage::<Bird>();
+      let bird = birds.get_mut(bird).unwrap();
+      bird.dy = dy;
+      bird.fly_pressed = true;
     }
+    (world, bird)
+  }
 
-    if let Some(entity) = e_sub_title {
-      hidden
-        .insert(entity, Hidden)
-        .expect("Error while trying to hide sub_title!");
+  #[test]
+  fn set_flap_replaces_dy() {
+    for &dy in &[-3., 0.5, 6.] {
+      let (mut world, bird) = flapping_bird_world(
+        GameConfig {
+          jump_mode: JumpMode::Set,
+          ..Default::default()
+        },
+        dy,
+      );
+      run_frame(&mut world, PHYSICS_STEP);
+      assert_close(bird_dy(&world, bird), BIRD_JUMP);
     }
   }
 
-  fn handle_event(
-    &mut self,
-    _data: StateData<'_, GameData<'_, '_>>,
-    event: MyStateEvent,
-  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
-    if let MyStateEvent::Window(event) = &event {
-      if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
-        return Trans::Quit;
+  #[test]
+  fn eased_flap_reaches_bird_jump_over_the_ease_steps() {
+    let gravity = BIRD_GRAVITY * PHYSICS_STEP;
+    for &start in &[-3., 1.] {
+      let (mut world, bird) = flapping_bird_world(
+        GameConfig {
+          jump_mode: JumpMode::Eased,
+          ..Default::default()
+        },
+        start,
+      );
+      let mut dy = start;
+      for remaining in (1..=JUMP_EASE_STEPS).rev() {
+        run_frame(&mut world, PHYSICS_STEP);
+        // Gravity still pulls first, then a share of what's left to `BIRD_JUMP` is added.
+        dy += gravity;
+        dy += (BIRD_JUMP - dy) / remaining as f32;
+        assert_close(bird_dy(&world, bird), dy);
       }
+      assert_close(dy, BIRD_JUMP);
+      run_frame(&mut world, PHYSICS_STEP);
+      assert_close(bird_dy(&world, bird), BIRD_JUMP + gravity);
     }
-    if let MyStateEvent::Game(GameEvent::Collision) = event {
-      return Trans::Push(Box::new(PauseState));
-    }
-    Trans::None
   }
 
-  fn update(
-    &mut self,
-    data: StateData<'_, GameData<'a, 'b>>,
-  ) -> Trans<GameData<'a, 'b>, MyStateEvent> {
-    let mut rand = self.rand.unwrap_or(thread_rng());
-    if let Some(mut timer) = self.pipe_spawn_timer.take() {
-      {
-        let time = data.world.fetch::<Time>();
-        timer -= time.delta_seconds();
-      }
-      if timer <= 0.0 {
-        if let Some(sprite) = self.pipe_sprite.clone() {
-          let rand_bot = rand.gen_range(-40., -20.);
-          let rand_top = rand.gen_range(20., 40.);
-          let random_y = rand.gen_range(rand_bot, rand_top);
-          data
-            .world
-            .create_entity()
-            .with(Pipe::default())
-            .with(sprite.clone())
-            .with(Transform::from(Vector3::new(
-              VIRTUAL_WIDTH / 2. + PIPE_WIDTH,
-              -VIRTUAL_HEIGHT / 2. + random_y - PIPE_GAP / 2.,
-              3.,
-            )))
-            .build();
-          data
-            .world
-            .create_entity()
-            .with(Pipe::default())
-            .with(sprite)
-            .with({
-              let mut transform = Transform::from(Vector3::new(
-                VIRTUAL_WIDTH / 2. + PIPE_WIDTH,
-                VIRTUAL_HEIGHT / 2. + random_y + PIPE_GAP / 2.,
-                3.,
-              ));
-              transform.set_rotation_2d(std::f32::consts::PI);
-              transform
-            })
-            .build();
-        }
-        self.pipe_spawn_timer.replace(rand.gen_range(2., 4.));
-      } else {
-        self.pipe_spawn_timer.replace(timer);
-      }
+  #[test]
+  fn ceiling_wrap_threshold() {
+    let threshold = VIRTUAL_HEIGHT / 2. + BIRD_WIDTH / 2.;
+    let floor = VIRTUAL_HEIGHT / -2. + GROUND_HEIGHT + BIRD_HEIGHT / 2.;
+    // Rising after this step's gravity, the wrap has to keep it.
+    let dy = 3. + BIRD_GRAVITY * PHYSICS_STEP;
+    for &(ceiling_wrap, end_y, expected_y) in &[
+      (true, threshold - 0.5, threshold - 0.5),
+      (true, threshold + 0.5, floor),
+      (false, threshold + 0.5, threshold + 0.5),
+    ] {
+      let (mut world, bird) = bird_world(GameConfig {
+        ceiling_wrap,
+        ..Default::default()
+      });
+      world.write_storage::<Bird>().get_mut(bird).unwrap().dy = 3.;
+      world
+        .write_storage::<Transform>()
+        .get_mut(bird)
+        .unwrap()
+        .set_translation_y(end_y - dy);
+      run_frame(&mut world, PHYSICS_STEP);
+      assert_close(bird_y(&world, bird), expected_y);
+      assert_close(bird_dy(&world, bird), dy);
     }
+  }
+
+  /// A world the `CollisionSystem` can run in, with nothing but a bird at `y` and no assets.
+  fn collision_world(config: GameConfig, y: f32) -> (World, Entity) {
+    let mut world = World::new();
+    System::setup(&mut CollisionSystem, &mut world);
+    world.insert(config);
+    let text = world.create_entity().build();
+    world.insert(Score { text });
+    world.insert(Coins { text });
+    world.insert(GravityFlip {
+      remaining: 0.,
+      text,
+    });
+    world.insert(Rewind { charges: 0, text });
+    world.insert(NoClip {
+      active: false,
+      text,
+    });
+    let bird = world
+      .create_entity()
+      .with(Bird::default())
+      .with(Transform::from(Vector3::new(0., y, Z_BIRD)))
+      .build();
+    (world, bird)
+  }
+
+  /// Runs the `CollisionSystem` once and counts the collisions it reported.
+  fn collisions(world: &mut World) -> usize {
+    let mut reader = world
+      .write_resource::<EventChannel<GameEvent>>()
+      .register_reader();
+    CollisionSystem.run_now(world);
+    world
+      .read_resource::<EventChannel<GameEvent>>()
+      .read(&mut reader)
+      .filter(|event| matches!(event, GameEvent::Collision(_)))
+      .count()
+  }
 
-    if let Some(dispatcher) = self.dispatcher.as_mut() {
-      dispatcher.dispatch(&data.world);
+  #[test]
+  fn overlapping_both_pipes_of_a_pair_collides_once() {
+    let (mut world, _) = collision_world(GameConfig::default(), 0.);
+    // A gap narrower than the bird, it sticks into the lower and the upper pipe.
+    for &y in &[PIPE_HEIGHT / -2. + 4., PIPE_HEIGHT / 2. - 4.] {
+      world
+        .create_entity()
+        .with(Pipe::default())
+        .with(Transform::from(Vector3::new(0., y, Z_PIPE)))
+        .build();
     }
-    data.data.update(&data.world);
-    Trans::None
+    assert_eq!(collisions(&mut world), 1);
   }
-}
 
-#[derive(Default)]
-struct PauseState;
+  #[test]
+  fn score_stops_at_the_cap() {
+    let add = |score, points, max| add_score(score, points, max, ScoreFormat::Plain);
+    assert_eq!(add(8, 1, Some(10)), (9, "9".to_string()));
+    assert_eq!(add(9, 1, Some(10)), (10, "10 MAX".to_string()));
+    assert_eq!(add(10, 1, Some(10)), (10, "10 MAX".to_string()));
+    // A combo multiplier jumping over the cap still lands on it.
+    assert_eq!(add(9, 3, Some(10)), (10, "10 MAX".to_string()));
+  }
 
-impl<'a, 'b> State<GameData<'a, 'b>, MyStateEvent> for PauseState {
-  fn handle_event(
-    &mut self,
-    _data: StateData<'_, GameData<'a, 'b>>,
-    event: MyStateEvent<StringBindings>,
-  ) -> Trans<GameData<'a, 'b>, MyStateEvent<StringBindings>> {
-    if let MyStateEvent::Window(event) = &event {
-      if is_key_down(&event, VirtualKeyCode::Space) {
-        Trans::Pop
-      } else if is_close_requested(&event) || is_key_down(&event, VirtualKeyCode::Escape) {
-        Trans::Quit
-      } else {
-        Trans::None
+  #[test]
+  fn uncapped_score_never_turns_negative() {
+    let add = |score, points| add_score(score, points, None, ScoreFormat::Plain);
+    assert_eq!(add(i32::MAX - 1, 3), (i32::MAX, i32::MAX.to_string()));
+    assert_eq!(add(i32::MAX, 1), (i32::MAX, i32::MAX.to_string()));
+  }
+
+  #[test]
+  fn dj_stays_silent_without_tracks() {
+    let mut music = Music {
+      music: Vec::new().into_iter().cycle(),
+      tracks: Vec::new(),
+      loading: ProgressCounter::new(),
+    };
+    assert!(next_track(&mut music).is_none());
+    assert!(next_track(&mut music).is_none());
+  }
+
+  #[test]
+  fn picked_gaps_stay_within_the_limits() {
+    let mut rand = StdRng::seed_from_u64(177);
+    for &(gap_min, gap_max) in &[(MIN_PIPE_GAP, 60.), (40., 80.), (50., 50.)] {
+      for &hurry_up in &[0., 0.5, 1.] {
+        let shrink = 1. - hurry_up * HURRY_UP_GAP_SHRINK;
+        for _ in 0..100 {
+          let gap = pick_gap(&mut rand, gap_min, gap_max, shrink);
+          assert!(gap >= MIN_PIPE_GAP, "{} is below the minimum gap", gap);
+          assert!(gap >= (gap_min * shrink).max(MIN_PIPE_GAP) - 1e-4);
+          assert!(gap <= (gap_max * shrink).max(MIN_PIPE_GAP) + 1e-4);
+        }
       }
-    } else {
-      Trans::None
     }
   }
 
-  fn update(
-    &mut self,
-    data: StateData<'_, GameData<'a, 'b>>,
-  ) -> Trans<GameData<'a, 'b>, MyStateEvent<StringBindings>> {
-    data.data.update(&data.world);
-    Trans::None
+  #[test]
+  fn fixed_gap_leaves_the_random_sequence_alone() {
+    let mut rand = StdRng::seed_from_u64(177);
+    let mut untouched = rand.clone();
+    assert_close(pick_gap(&mut rand, 50., 50., 1.), 50.);
+    assert_close(
+      pick_gap(&mut rand, MIN_PIPE_GAP, MIN_PIPE_GAP, 0.7),
+      MIN_PIPE_GAP,
+    );
+    assert_eq!(rand.gen::<u64>(), untouched.gen::<u64>());
   }
-}
 
-fn point_in_rect(x: f32, y: f32, left: f32, bottom: f32, right: f32, top: f32) -> bool {
-  x >= left && x <= right && y >= bottom && y <= top
-}
+  #[test]
+  fn clamped_spawns_keep_every_pair_reachable() {
+    let mut rand = StdRng::seed_from_u64(149);
+    let (mut from_y, mut from_gap) = (0., PIPE_GAP);
+    let mut clamped = 0;
+    for _ in 0..500 {
+      let dx = rand.gen_range(PIPE_WIDTH, 240.);
+      let hurry_up = rand.gen_range(0., 0.5);
+      let speed = -PIPE_SCROLL * (1. + hurry_up);
+      let gap = pick_gap(
+        &mut rand,
+        MIN_PIPE_GAP,
+        PIPE_GAP,
+        1. - hurry_up * HURRY_UP_GAP_SHRINK,
+      );
+      let target = rand.gen_range(-100., 100.);
+      let y = clamp_to_reachable(target, from_y, from_gap, dx, speed, gap);
+      let (min_y, max_y) = reachable_gap_range(from_y, from_gap, dx, speed, gap);
+      assert!(
+        min_y <= y && y <= max_y,
+        "{} is out of {}..{}",
+        y,
+        min_y,
+        max_y
+      );
+      // Scrolling the other way spawns the next pair on the other side, it's clamped the same.
+      assert_close(
+        clamp_to_reachable(target, from_y, from_gap, -dx, speed, gap),
+        y,
+      );
+      if (y - target).abs() > 1e-4 {
+        clamped += 1;
+      }
+      from_y = y;
+      from_gap = gap;
+    }
+    assert!(clamped > 0, "no spawn needed the clamp");
+  }
 
-fn init_camera(world: &mut World) {
-  world
-    .create_entity()
-    .with(Camera::standard_2d(VIRTUAL_WIDTH, VIRTUAL_HEIGHT))
-    .with(Transform::from(Vector3::new(0., 0., 10.)))
-    .build();
-}
+  #[test]
+  fn gravity_wells_keep_the_despawn_margin() {
+    let mut world = World::new();
+    System::setup(&mut GravityWellSystem, &mut world);
+    let config = GameConfig::default();
+    let radius = 30.;
+    let edge = VIRTUAL_WIDTH / -2. - config.despawn_margin - radius;
+    world.insert(config);
+    let step = PIPE_SCROLL * PHYSICS_STEP;
+    // Past the edge of the screen but still within the margin, and one step from leaving it.
+    let wells: Vec<_> = [edge + 1. - step, edge - 1. - step]
+      .iter()
+      .map(|&x| {
+        world
+          .create_entity()
+          .with(GravityWell {
+            radius,
+            strength: 1.,
+            phase: 0.,
+          })
+          .with(Transform::from(Vector3::new(x, 0., 0.)))
+          .build()
+      })
+      .collect();
+    GravityWellSystem.run_now(&world);
+    world.maintain();
+    assert!(world.is_alive(wells[0]));
+    assert!(!world.is_alive(wells[1]));
+  }
 
-fn set_score_font(world: &World, str: &str) -> String {
-  let score = world.read_resource::<Score>();
-  let mut ui_text = world.write_storage::<UiText>();
-  if let Some(text) = ui_text.get_mut(score.text) {
-    let last_score = text.text.clone();
-    text.text = str.to_string();
-    return last_score;
+  /// Runs one physics step of the bird split into `substeps` like `PlayState` does, and counts the
+  /// collisions it reported.
+  fn substepped_collisions(world: &mut World, substeps: u32) -> usize {
+    let mut reader = world
+      .write_resource::<EventChannel<GameEvent>>()
+      .register_reader();
+    world.write_resource::<PhysicsClock>().substeps = substeps;
+    for substep in 0..substeps {
+      if world.read_resource::<ImpactPoint>().point.is_some() {
+        break;
+      }
+      world.write_resource::<PhysicsClock>().substep = substep;
+      BirdSystem.run_now(world);
+      CollisionSystem.run_now(world);
+    }
+    world
+      .read_resource::<EventChannel<GameEvent>>()
+      .read(&mut reader)
+      .filter(|event| matches!(event, GameEvent::Collision(_)))
+      .count()
   }
-  return "0".to_string();
-}
 
-fn load_sprite<T>(image: T, ron: T, number: usize, world: &World) -> SpriteRender
-where
-  T: Into<String>,
-{
-  let texture_handle = {
-    let loader = world.read_resource::<Loader>();
-    let texture_storage = world.read_resource::<AssetStorage<Texture>>();
-    loader.load(image, ImageFormat::default(), (), &texture_storage)
-  };
+  #[test]
+  fn substeps_keep_a_fast_bird_from_tunneling_through_the_ground() {
+    let ground_y = (VIRTUAL_HEIGHT - GROUND_HEIGHT) / -2.;
+    let (_, bottom, _, top) = GameConfig::default().ground_hitbox(0., ground_y);
+    // Right above the ground, a single step of the dive ends right below it.
+    let start = top + BIRD_HEIGHT / 2. + 1.;
+    let dy = -(top - bottom + BIRD_HEIGHT + 20.);
+    for &(substeps, hits) in &[(1, 0), (4, 1)] {
+      let config = GameConfig {
+        physics_substeps: substeps,
+        ..Default::default()
+      };
+      assert_eq!(config.substeps_for(dy), substeps);
+      let (mut world, bird) = collision_world(config, start);
+      System::setup(&mut BirdSystem, &mut world);
+      world.insert(PhysicsClock::default());
+      world
+        .create_entity()
+        .with(Background {
+          b_type: BackgroundType::Ground,
+          scroll_pos: 0.,
+          validated: true,
+        })
+        .with(Transform::from(Vector3::new(0., ground_y, Z_GROUND)))
+        .build();
+      world.write_storage::<Bird>().get_mut(bird).unwrap().dy = dy;
+      assert_eq!(substepped_collisions(&mut world, substeps), hits);
+    }
+  }
 
-  let sprite_handle = {
-    let loader = world.read_resource::<Loader>();
-    let sprite_sheet_store = world.read_resource::<AssetStorage<SpriteSheet>>();
-    loader.load(
-      ron,
-      SpriteSheetFormat(texture_handle),
-      (),
-      &sprite_sheet_store,
-    )
-  };
+  #[test]
+  fn slow_birds_are_not_substepped() {
+    let config = GameConfig {
+      physics_substeps: 4,
+      ..Default::default()
+    };
+    assert_eq!(config.substeps_for(config.substep_speed), 1);
+    assert_eq!(config.substeps_for(-config.substep_speed - 1.), 4);
+    assert_eq!(GameConfig::default().substeps_for(-100.), 1);
+  }
 
-  SpriteRender::new(sprite_handle, number)
-}
+  #[test]
+  fn impact_speed_maps_to_the_shake() {
+    let (scale, max) = (0.5, 6.);
+    for &(dy, magnitude) in &[
+      (3., 0.),
+      (0., 0.),
+      (-2., 1.),
+      (-8., 4.),
+      (-12., 6.),
+      (-40., 6.),
+    ] {
+      assert_close(shake_magnitude(dy, scale, max), magnitude);
+    }
+    assert_close(shake_magnitude(-40., 0., max), 0.);
+  }
 
-fn load_audio_track_wav(loader: &Loader, world: &World, file: &str) -> SourceHandle {
-  loader.load(file, WavFormat, (), &world.read_resource())
-}
+  struct MockVolume(Option<f32>);
 
-fn load_audio_track_mp3(loader: &Loader, world: &World, file: &str) -> SourceHandle {
-  loader.load(file, Mp3Format, (), &world.read_resource())
-}
+  impl SystemVolume for MockVolume {
+    fn level(&self) -> Option<f32> {
+      self.0
+    }
+  }
 
-fn init_audio(world: &mut World) {
-  let (sound_effects, music) = {
-    let loader = world.read_resource::<Loader>();
+  #[test]
+  fn music_follows_the_system_volume() {
+    for &(level, volume) in &[
+      (None, MUSIC_VOLUME),
+      (Some(1.), MUSIC_VOLUME),
+      (Some(0.5), MUSIC_VOLUME / 2.),
+      (Some(0.), 0.),
+      (Some(1.5), MUSIC_VOLUME),
+      (Some(-0.2), 0.),
+    ] {
+      assert_close(music_volume(&MockVolume(level)), volume);
+    }
+    assert_close(music_volume(system_volume(false).as_ref()), MUSIC_VOLUME);
+  }
 
-    let mut sink = world.write_resource::<AudioSink>();
-    sink.set_volume(0.125);
+  #[test]
+  fn pactl_volume_is_parsed() {
+    let output = "Volume: front-left: 32768 /  50% / -18.06 dB,   front-right: 19661 /  30% / \
+                  -31.37 dB\n        balance -0.40\n";
+    assert_close(parse_pactl_volume(output).unwrap(), 0.4);
+    assert_eq!(parse_pactl_volume("No such entity"), None);
+  }
 
-    let music = MUSIC_TRACKS
-      .iter()
-      .map(|file| load_audio_track_mp3(&loader, &world, file))
-      .collect::<Vec<_>>()
-      .into_iter()
-      .cycle();
-    let music = Music { music };
+  #[test]
+  fn share_cards_are_named_by_time_and_score() {
+    assert_eq!(
+      share_card_path(1_600_000_000, 42),
+      "screenshots/2020-09-13_122640_score_42.png"
+    );
+    assert_eq!(
+      share_card_path(0, 0),
+      "screenshots/1970-01-01_000000_score_0.png"
+    );
+  }
 
-    let sound = Sounds {
-      score_sfx: load_audio_track_wav(&loader, &world, SCORE_SOUND),
-      hurt_sfx: load_audio_track_wav(&loader, &world, HURT_SOUND),
-      explosion_sfx: load_audio_track_wav(&loader, &world, EXPLOSION_SOUND),
-      jump_sfx: load_audio_track_wav(&loader, &world, JUMP_SOUND),
+  #[test]
+  fn share_card_shows_the_run() {
+    let font = Font::try_from_bytes(include_bytes!("../assets/font/flappy.ttf")).unwrap();
+    let background = RgbaImage::from_pixel(1157, 288, Rgba([80, 160, 220, 255]));
+    let logo = RgbaImage::from_pixel(109, 28, Rgba([255, 200, 0, 255]));
+    let card = |score, new_best| {
+      let run = FinishedRun {
+        result: RunResult {
+          score,
+          duration: 12.5,
+          seed: 2020,
+          coins: 3,
+          completed: false,
+        },
+        mode: GameMode::Classic,
+        new_best,
+      };
+      share_card(&run, &background, &logo, &font)
     };
+    let (one, two) = (card(1, false), card(2, false));
+    assert_eq!(
+      one.dimensions(),
+      (
+        VIRTUAL_WIDTH as u32 * SHARE_CARD_SCALE,
+        VIRTUAL_HEIGHT as u32 * SHARE_CARD_SCALE
+      )
+    );
+    // The corners are plain background, only the score text tells the cards apart.
+    assert_eq!(*one.get_pixel(0, 0), Rgba([80, 160, 220, 255]));
+    assert!(one.pixels().zip(two.pixels()).any(|(a, b)| a != b));
 
-    (sound, music)
-  };
-
-  world.insert(sound_effects);
-  world.insert(music);
-}
-
-fn play_score_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
-  if let Some(ref output) = output.as_ref() {
-    if let Some(sound) = storage.get(&sounds.score_sfx) {
-      output.play_once(sound, 0.25);
-    }
+    // A new best gets the banner above the score.
+    let best = card(1, true);
+    let banner_rows = VIRTUAL_HEIGHT as u32 * SHARE_CARD_SCALE / 2 - 90;
+    let differs = |y| (0..best.width()).any(|x| best.get_pixel(x, y) != one.get_pixel(x, y));
+    assert!((0..banner_rows).any(differs));
+    assert!(!(banner_rows..best.height()).any(differs));
   }
-}
 
-fn play_hurt_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
-  if let Some(ref output) = output.as_ref() {
-    if let Some(sound) = storage.get(&sounds.hurt_sfx) {
-      output.play_once(sound, 0.25);
-    }
-    if let Some(sound) = storage.get(&sounds.explosion_sfx) {
-      output.play_once(sound, 0.25);
+  #[test]
+  fn share_codes_round_trip() {
+    for &(seed, score) in &[(0, 0), (2020, 17), (std::u64::MAX, -3)] {
+      let code = share_code(seed, score);
+      assert_eq!(parse_share_code(&code), Some((seed, score)));
+      assert_eq!(
+        parse_share_code(&format!("  {}\n", code)),
+        Some((seed, score))
+      );
     }
+    assert_eq!(share_code(2020, 17), "flippy:7e4:17");
   }
-}
 
-fn play_jump_sound(sounds: &Sounds, storage: &AssetStorage<Source>, output: Option<&Output>) {
-  if let Some(ref output) = output.as_ref() {
-    if let Some(sound) = storage.get(&sounds.jump_sfx) {
-      output.play_once(sound, 0.15);
+  #[test]
+  fn broken_share_codes_are_rejected() {
+    for code in &[
+      "",
+      "flippy",
+      "flippy:7e4",
+      "flappy:7e4:17",
+      "flippy:xyz:17",
+      "flippy:7e4:many",
+      "flippy:7e4:17:3",
+    ] {
+      assert_eq!(parse_share_code(code), None, "{:?} was accepted", code);
     }
   }
-}
-
-fn main() -> amethyst::Result<()> {
-  amethyst::start_logger(Default::default());
 
-  let app_root = application_root_dir()?;
-  let display_conf_path = app_root.join("config/display.ron");
-  let assets_dir = app_root.join("assets");
+  #[test]
+  fn dead_zone_swallows_drift_and_keeps_full_presses() {
+    assert_eq!(apply_dead_zone(0.1, 0.15), 0.);
+    assert_eq!(apply_dead_zone(-0.15, 0.15), 0.);
+    assert_eq!(apply_dead_zone(1., 0.15), 1.);
+    assert_eq!(apply_dead_zone(-1., 0.15), -1.);
+    assert!((apply_dead_zone(0.575, 0.15) - 0.5).abs() < 1e-6);
+  }
 
-  let game_data = GameDataBuilder::default()
-    .with_system_desc(
-      DjSystemDesc::new(|music: &mut Music| music.music.next()),
-      "dj_system",
-      &[],
-    )
-    .with(BackgroundSystem, "background_system", &[])
-    .with_bundle(TransformBundle::new())?
-    .with_bundle(InputBundle::<StringBindings>::new())?
-    .with_bundle(UiBundle::<StringBindings>::new())?
-    .with_bundle(AudioBundle::default())?
-    .with_bundle(
-      RenderingBundle::<DefaultBackend>::new()
-        .with_plugin(
-          RenderToWindow::from_config_path(display_conf_path)?.with_clear([0.0, 0.0, 0.0, 1.0]),
-        )
-        .with_plugin(RenderUi::default())
-        .with_plugin(RenderFlat2D::default()),
-    )?;
-  let mut game = CoreApplication::<_, MyStateEvent, MyStateEventReader>::build(
-    assets_dir,
-    TitleScreenState::default(),
-  )?
-  .build(game_data)?;
-  game.run();
-  Ok(())
+  #[test]
+  fn axis_jitter_near_the_threshold_flaps_once() {
+    let mut bird = Bird::default();
+    let steps = [0.1, 0.14, 0.12, 0.7, 0.55, 0.62, 0.4, 0.3, 0.58];
+    let flaps = steps
+      .iter()
+      .map(|value| {
+        bird.stick_down = stick_flap_down(*value, bird.stick_down, 0.15, 0.5);
+        bird.flap_triggered(bird.stick_down, FlapTrigger::Press)
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      flaps,
+      [false, false, false, true, false, false, false, false, true]
+    );
+  }
 }